@@ -0,0 +1,72 @@
+//! Loading, wrapping for the screen and saving a TODO file of 5000 items,
+//! with a comment every 50 and a subtask every 10. Run with `cargo bench`
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use tick::file::{Line, TodoFile, read_todos, write_todos};
+use tick::item::{Format, Kind, Prefixes, entry_text};
+use tick::text::split_to_fit;
+
+const ITEMS: usize = 5000;
+
+fn large_file() -> String {
+    let mut contents = String::new();
+    for i in 0..ITEMS {
+        if i % 50 == 0 {
+            contents += &format!("# section {}\n", i / 50);
+        }
+        let indent = if i % 10 == 9 { "  " } else { "" };
+        contents += &format!("{indent}TODO: item {i} @due:2026-{:02}-01\n", i % 12 + 1);
+    }
+    contents
+}
+
+/// Screen rows the items take in the left column of an 80 column terminal,
+/// laid out as drawing does before anything is cached
+fn wrap(items: &[String]) -> usize {
+    items
+        .iter()
+        .map(|entry| {
+            let line = format!("[ ] {}", entry_text(entry));
+            1 + split_to_fit(&line, 39, 4).1.len()
+        })
+        .sum()
+}
+
+fn save(loaded: &TodoFile, todos: &[String], prefixes: &Prefixes) -> Vec<Line> {
+    let mut out = Vec::new();
+    let sections = [(Kind::Todo, todos)];
+    write_todos(&mut out, Format::Tick, prefixes, &loaded.layout, &sections).unwrap()
+}
+
+fn large_list(c: &mut Criterion) {
+    let prefixes = Prefixes::default();
+    let contents = large_file();
+    c.bench_function("load 5000 items", |b| {
+        b.iter(|| read_todos(black_box(contents.as_bytes()), Format::Tick, &prefixes))
+    });
+
+    let loaded = read_todos(contents.as_bytes(), Format::Tick, &prefixes);
+    // Every tenth item long enough to wrap over a few rows
+    let long: Vec<_> = (loaded.todos.iter().enumerate())
+        .map(|(i, entry)| match i % 10 {
+            0 => format!("{entry} {}", "wraps over several rows ".repeat(6)),
+            _ => entry.clone(),
+        })
+        .collect();
+    c.bench_function("wrap 5000 items", |b| b.iter(|| wrap(black_box(&long))));
+    c.bench_function("save 5000 unchanged items", |b| {
+        b.iter(|| save(&loaded, black_box(&loaded.todos), &prefixes))
+    });
+
+    // Every line moves, so each item has to be matched to a new one
+    let mut reordered = loaded.todos.clone();
+    reordered.reverse();
+    c.bench_function("save 5000 reordered items", |b| {
+        b.iter(|| save(&loaded, black_box(&reordered), &prefixes))
+    });
+}
+
+criterion_group!(benches, large_list);
+criterion_main!(benches);
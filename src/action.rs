@@ -1,25 +1,94 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::bail;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
+#[derive(Clone, Copy)]
 pub enum TabAction {
     Toggle,
     Left,
     Right,
+    /// Jump straight to the tab at this 1-based position
+    Index(u8),
 }
 
+#[derive(Clone, Copy)]
 pub enum Action {
     Enter,
+    EnterStay,
     SwitchTab(TabAction),
     Insert(KeyCode),
     Edit,
+    ClearEdit,
     MoveCursor(KeyCode),
+    /// Move the cursor half a screen, or a whole one with `FullPage`
+    HalfPage(KeyCode),
+    FullPage(KeyCode),
     MoveItem(KeyCode),
+    /// Move the item to the top or bottom of its list in one go
+    MoveItemToEnd(KeyCode),
     GotoBegin,
     GotoEnd,
     Delete,
+    /// `d`, waiting for a motion that says which items to delete
+    DeleteOperator,
     SaveQuit,
     NoSaveQuit,
     ShowHelp,
     ShowNumber,
+    SelectRegister,
+    Yank,
+    Paste(KeyCode),
+    /// Yank / paste below through the system clipboard, like `"+y` / `"+p`
+    YankClipboard,
+    PasteClipboard,
+    Archive,
+    ShowArchived,
+    ToggleSomeday,
+    ShuttleSomeday,
+    ExternalEdit,
+    InsertHeading,
+    Undo,
+    Redo,
+    CommandLine,
+    MoveToOtherTab,
+    SetDue,
+    /// Delete every item of the active tab, once confirmed
+    ClearList,
+    /// Save and switch to another TODO file, typed on the command line
+    OpenFile,
+    /// Show only the items carrying a `#tag`, or every item again
+    FilterTag,
+    /// Show only the todos due today or earlier, or every item again
+    FilterDue,
+    /// Show the whole text of the item under the cursor in a box
+    ShowFullText,
+    /// Select a range of items to delete, complete, yank or move at once
+    Visual,
+    ToggleTruncate,
+    /// Show only the active tab, across the whole width, or both columns again
+    ToggleSingleColumn,
+    Search,
+    /// Sort the current list A-Z, or Z-A when it already is
+    Sort,
+    /// Step the priority of the item under the cursor: none, A, B, C, none
+    CyclePriority,
+    /// Make the item a subtask of the one above it, or with `false` an item
+    /// of its own again
+    Indent(bool),
+    /// Hide or show the subtasks of the item under the cursor
+    ToggleCollapse,
+    /// Move higher priority items to the top of the current list
+    SortPriority,
+    /// Jump to the next match of the last search, or the previous one with `true`
+    SearchNext(bool),
+    /// Jump to the next item due today or earlier
+    JumpDue,
+    /// Jump to the next item of the highest priority
+    JumpPriority,
+    /// Clear the screen and draw it again from scratch
+    Redraw,
 }
 
 impl TryFrom<KeyEvent> for Action {
@@ -35,24 +104,38 @@ impl TryFrom<KeyEvent> for Action {
         Ok(match event.code {
             // ── Core actions ────────────────────────────────
             Enter => Self::Enter,
+            Char('t') => Self::EnterStay,
             Tab => Self::SwitchTab(TA::Toggle),
             F(1) => Self::ShowHelp,
+            // F2-F12 are left for the user, see `from_key`
+            Char('l') if m.contains(M::CONTROL) => Self::Redraw,
             Char('l') => Self::SwitchTab(TA::Right),
             Char('h') => Self::SwitchTab(TA::Left),
             Right if !m.contains(M::SHIFT) => Self::SwitchTab(TA::Right),
             Left if !m.contains(M::SHIFT) => Self::SwitchTab(TA::Left),
-            Char('n') => Self::ShowNumber,
+            Char(c @ '1'..='9') if m.contains(M::ALT) => Self::SwitchTab(TA::Index(c as u8 - b'0')),
 
             // ── Insert / Edit ───────────────────────────────
             Char('i') => Self::Insert(Up),
             Char('o') => Self::Insert(Down),
+            Char('e') if m.contains(M::CONTROL) => Self::ExternalEdit,
             Char('e') => Self::Edit,
+            Char('C') => Self::ClearEdit,
+            Char('H') => Self::InsertHeading,
 
             // ── Navigation ─────────────────────────────────
-            // Vim keys
+            // Vim keys, Ctrl moving the item to the end of the list instead
+            Char('k') if m.contains(M::CONTROL) => Self::MoveItemToEnd(Up),
+            Char('j') if m.contains(M::CONTROL) => Self::MoveItemToEnd(Down),
             Char('k') => Self::MoveCursor(Up),
             Char('j') => Self::MoveCursor(Down),
 
+            // Paging, ahead of the plain letters
+            Char('d') if m.contains(M::CONTROL) => Self::HalfPage(Down),
+            Char('u') if m.contains(M::CONTROL) => Self::HalfPage(Up),
+            Char('f') if m.contains(M::CONTROL) => Self::FullPage(Down),
+            Char('b') if m.contains(M::CONTROL) => Self::FullPage(Up),
+
             // Arrow keys
             Up if !m.contains(M::SHIFT) => Self::MoveCursor(Up),
             Down if !m.contains(M::SHIFT) => Self::MoveCursor(Down),
@@ -69,13 +152,43 @@ impl TryFrom<KeyEvent> for Action {
             // ── Jumping ────────────────────────────────────
             Char('g') => Self::GotoBegin,
             Char('G') => Self::GotoEnd,
+            Char('/') => Self::Search,
+            Char('n') => Self::SearchNext(false),
+            Char('N') => Self::SearchNext(true),
+            Char('@') => Self::JumpDue,
+            Char('^') => Self::JumpPriority,
 
             // ── Item / Buffer management ───────────────────
-            Char('d') => Self::Delete,
+            Char('d') => Self::DeleteOperator,
+            Char('u') => Self::Undo,
+            Char('r') if m.contains(M::CONTROL) => Self::Redo,
+            Char('a') => Self::Archive,
+            Char('A') => Self::ShowArchived,
+            Char('S') => Self::ToggleSomeday,
+            Char('M') => Self::ShuttleSomeday,
+            Char('m') => Self::MoveToOtherTab,
+            Char('D') => Self::SetDue,
+            Char('#') => Self::FilterTag,
+            Char('T') => Self::FilterDue,
+            Char('v') => Self::ShowFullText,
+            Char('V') => Self::Visual,
+            Char('s') => Self::Sort,
+            Char('!') => Self::CyclePriority,
+            Char('>') => Self::Indent(true),
+            Char('<') => Self::Indent(false),
+            Char('z') => Self::ToggleCollapse,
+
+            // ── Registers ──────────────────────────────────
+            Char('"') => Self::SelectRegister,
+            Char('y') => Self::Yank,
+            Char('Y') => Self::YankClipboard,
+            Char('p') => Self::Paste(Down),
+            Char('P') => Self::Paste(Up),
 
             // ── Quit ───────────────────────────────────────
             Char('q') => Self::SaveQuit,
             Char('Q') => Self::NoSaveQuit,
+            Char(':') => Self::CommandLine,
             Char('c') if m.contains(M::CONTROL) => Self::NoSaveQuit,
 
             // ── Fallback ───────────────────────────────────
@@ -84,25 +197,319 @@ impl TryFrom<KeyEvent> for Action {
     }
 }
 
+/// Names used to bind actions in the config file
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        use KeyCode::{Down, Up};
+
+        Ok(match s {
+            "toggle" => Self::Enter,
+            "toggle_stay" => Self::EnterStay,
+            "switch_tab" => Self::SwitchTab(TabAction::Toggle),
+            "insert_above" => Self::Insert(Up),
+            "insert_below" => Self::Insert(Down),
+            "insert_heading" => Self::InsertHeading,
+            "edit" => Self::Edit,
+            "clear_edit" => Self::ClearEdit,
+            "goto_begin" => Self::GotoBegin,
+            "goto_end" => Self::GotoEnd,
+            "half_page_down" => Self::HalfPage(Down),
+            "half_page_up" => Self::HalfPage(Up),
+            "page_down" => Self::FullPage(Down),
+            "page_up" => Self::FullPage(Up),
+            "move_to_top" => Self::MoveItemToEnd(Up),
+            "move_to_bottom" => Self::MoveItemToEnd(Down),
+            "search" => Self::Search,
+            "search_next" => Self::SearchNext(false),
+            "search_prev" => Self::SearchNext(true),
+            "jump_due" => Self::JumpDue,
+            "jump_priority" => Self::JumpPriority,
+            "delete" => Self::Delete,
+            "undo" => Self::Undo,
+            "redo" => Self::Redo,
+            "yank" => Self::Yank,
+            "paste_below" => Self::Paste(Down),
+            "paste_above" => Self::Paste(Up),
+            "yank_clipboard" => Self::YankClipboard,
+            "paste_clipboard" => Self::PasteClipboard,
+            "archive" => Self::Archive,
+            "show_archived" => Self::ShowArchived,
+            "show_someday" => Self::ToggleSomeday,
+            "move_to_someday" => Self::ShuttleSomeday,
+            "move_to_other_tab" => Self::MoveToOtherTab,
+            "set_due" => Self::SetDue,
+            "open_file" => Self::OpenFile,
+            "clear_list" => Self::ClearList,
+            "filter_tag" => Self::FilterTag,
+            "filter_due" => Self::FilterDue,
+            "show_full_text" => Self::ShowFullText,
+            "visual" => Self::Visual,
+            "toggle_truncate" => Self::ToggleTruncate,
+            "toggle_single_column" => Self::ToggleSingleColumn,
+            "sort" => Self::Sort,
+            "cycle_priority" => Self::CyclePriority,
+            "indent" => Self::Indent(true),
+            "outdent" => Self::Indent(false),
+            "toggle_collapse" => Self::ToggleCollapse,
+            "sort_priority" => Self::SortPriority,
+            "external_edit" => Self::ExternalEdit,
+            "show_number" => Self::ShowNumber,
+            "redraw" => Self::Redraw,
+            "help" => Self::ShowHelp,
+            "save_quit" => Self::SaveQuit,
+            "quit_no_save" => Self::NoSaveQuit,
+            _ => bail!("Unknown action {:?}", s),
+        })
+    }
+}
+
+impl Action {
+    /// Whether the action changes the lists, and so gets an undo entry
+    pub fn is_mutating(&self) -> bool {
+        matches!(
+            self,
+            Self::Enter
+                | Self::EnterStay
+                | Self::Insert(_)
+                | Self::Edit
+                | Self::ClearEdit
+                | Self::InsertHeading
+                | Self::MoveItem(_)
+                | Self::MoveItemToEnd(_)
+                | Self::Delete
+                | Self::Paste(_)
+                | Self::PasteClipboard
+                | Self::Archive
+                | Self::ShuttleSomeday
+                | Self::MoveToOtherTab
+                | Self::ExternalEdit
+                | Self::Sort
+                | Self::CyclePriority
+                | Self::SortPriority
+                | Self::Indent(_)
+        )
+    }
+
+    /// Maps a key through the user's function key bindings, falling back to
+    /// the default keymap
+    pub fn from_key(event: KeyEvent, function_keys: &HashMap<u8, Action>) -> Result<Self, ()> {
+        if let KeyCode::F(n) = event.code
+            && let Some(&action) = function_keys.get(&n)
+        {
+            return Ok(action);
+        }
+        Self::try_from(event)
+    }
+
+    /// Less common actions, reached by pressing the leader key first
+    pub fn from_leader(event: KeyEvent) -> Option<Self> {
+        use KeyCode::Char;
+
+        Some(match event.code {
+            Char('a') => Self::Archive,
+            Char('A') => Self::ShowArchived,
+            Char('s') => Self::ToggleSomeday,
+            Char('m') => Self::ShuttleSomeday,
+            Char('e') => Self::ExternalEdit,
+            Char('n') => Self::ShowNumber,
+            Char('w') => Self::ToggleTruncate,
+            Char('f') => Self::ToggleSingleColumn,
+            Char('p') => Self::SortPriority,
+            Char('d') => Self::JumpDue,
+            Char('t') => Self::FilterDue,
+            Char('!') => Self::JumpPriority,
+            Char('o') => Self::OpenFile,
+            Char('c') => Self::ClearList,
+            _ => return None,
+        })
+    }
+}
+
 pub enum InsertAction {
     Char(char),
     DeleteChar,
+    /// Deletes the word before the cursor
+    DeleteWord,
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    Home,
+    End,
+    /// Types the text of the unnamed register at the cursor
+    PasteRegister,
     Enter,
     Cancel,
+    Quit,
 }
 
 impl TryFrom<KeyEvent> for InsertAction {
     type Error = ();
 
     fn try_from(event: KeyEvent) -> Result<Self, Self::Error> {
-        use KeyCode::{Backspace, Char, Enter, Esc};
+        use KeyCode::{Backspace, Char, End, Enter, Esc, Home, Left, Right};
 
         Ok(match event.code {
+            Char('c') if event.modifiers.contains(KeyModifiers::CONTROL) => Self::Quit,
+            Char('w') if event.modifiers.contains(KeyModifiers::CONTROL) => Self::DeleteWord,
+            Char('r') if event.modifiers.contains(KeyModifiers::CONTROL) => Self::PasteRegister,
+            Char('b') if event.modifiers.contains(KeyModifiers::ALT) => Self::WordLeft,
+            Char('f') if event.modifiers.contains(KeyModifiers::ALT) => Self::WordRight,
             Char(c) => Self::Char(c),
             Backspace => Self::DeleteChar,
+            Left => Self::Left,
+            Right => Self::Right,
+            Home => Self::Home,
+            End => Self::End,
             Enter => Self::Enter,
             Esc => Self::Cancel,
             _ => return Err(()),
         })
     }
 }
+
+/// Key descriptions grouped by section, shared by the help screen and `--keys`
+const KEY_HELP: &[(&str, &[(&str, &str)])] = &[
+    (
+        "ACTIONS",
+        &[
+            ("f1", "Show this screen"),
+            ("Enter", "Move item to other tab and follow it"),
+            ("t", "Move item to other tab and stay"),
+            ("m", "Move item to the same spot in the other tab"),
+            ("i / o", "Insert item above / below"),
+            ("e", "Edit item under cursor"),
+            ("C", "Clear item under cursor and edit it"),
+            ("H", "Insert heading below"),
+            ("Ctrl-e", "Open the file in $EDITOR and reload it"),
+            ("<n>J / <n>K", "Move item under cursor down / up, n times"),
+            (
+                "Ctrl-j / Ctrl-k",
+                "Move item under cursor to the bottom / top",
+            ),
+            ("y", "Yank item under cursor"),
+            ("<n>dd", "Delete n items, keeping them for paste"),
+            ("dj / dk", "Delete this item and the one below / above"),
+            (
+                "dG / dg",
+                "Delete up to the end / start of the list, Esc cancels",
+            ),
+            ("p / P", "Paste yanked items below / above"),
+            ("u / Ctrl-r", "Undo / redo"),
+            ("v", "Show the full text of the item under cursor"),
+            (
+                "V",
+                "Select items, j / k extend, d / Enter / t / y / J / K apply, Esc cancels",
+            ),
+            ("s", "Sort list A-Z, again for Z-A (headings stay put)"),
+            ("!", "Cycle priority: (A), (B), (C), none"),
+            (
+                "> / <",
+                "Make item a subtask of the one above / an item again",
+            ),
+            ("z", "Collapse / expand the subtasks of the item"),
+            ("D", "Set due date (:due YYYY-MM-DD, empty to clear)"),
+            ("#", "Filter by tag (:tag name, empty to show all)"),
+            ("T", "Show only todos due by today, again to show all"),
+            ("\"<a-z>", "Use register for next yank / paste"),
+            (
+                "Y / \"+p",
+                "Copy item to / paste lines from the system clipboard",
+            ),
+            ("a", "Archive item (restore it while viewing archived)"),
+            ("A", "Show / hide archived items"),
+            ("S", "Show / hide someday items"),
+            ("M", "Move item to someday (back to todos from there)"),
+            ("q", "Save and quit"),
+            ("Q", "Quit without saving"),
+            (
+                ":w / :q / :wq / :q!",
+                "Save / save and quit / same / quit without saving",
+            ),
+            (":dedupe", "Remove later copies of items from the list"),
+        ],
+    ),
+    (
+        "MOVEMENT",
+        &[
+            ("<n>j / <n>k", "Move cursor down / up, n times"),
+            ("g / G", "Jump to beginning / end"),
+            ("<n>g / <n>G", "Jump to the n-th item, Esc drops the number"),
+            ("Ctrl-d / Ctrl-u", "Move half a screen down / up"),
+            ("Ctrl-f / Ctrl-b", "Move a whole screen down / up"),
+            (
+                "/",
+                "Fuzzy search the current tab (bm finds buy milk), Enter jumps to the best match",
+            ),
+            ("n / N", "Jump to next / previous match"),
+            (
+                "@ / ^",
+                "Jump to the next item due by today / of priority (A)",
+            ),
+            ("Tab", "Toggle Tab"),
+            ("<- / ->", "Change to the left/right tab"),
+            ("Ctrl-l", "Clear and redraw the screen"),
+            ("Alt-1..3", "Jump to todo / done / someday tab"),
+        ],
+    ),
+    (
+        "LEADER (leader key, space by default, then key)",
+        &[
+            ("a / A", "Archive item / show archived items"),
+            ("s / m", "Show someday items / move item to someday"),
+            ("e", "Open the file in $EDITOR"),
+            ("n", "Cycle item numbers: absolute, relative, off"),
+            ("w", "Toggle cutting long items to one line"),
+            ("f", "Toggle showing only the active tab, full width"),
+            ("p", "Sort list by priority, highest first"),
+            ("t", "Show only todos due by today, again to show all"),
+            (
+                "d / !",
+                "Jump to the next item due by today / of priority (A)",
+            ),
+            ("o", "Save and open another file (:e path)"),
+            ("c", "Delete every item of the tab, after asking"),
+        ],
+    ),
+    (
+        "INSERT / EDIT MODE",
+        &[
+            ("(type normally)", "Edit text"),
+            ("<- / ->", "Move the cursor"),
+            ("Alt-b / Alt-f", "Move the cursor a word back / forward"),
+            ("Ctrl-w", "Delete the word before the cursor"),
+            (
+                "Ctrl-r",
+                "Type the last yanked or deleted items at the cursor",
+            ),
+            ("Home / End", "Jump to start / end of the text"),
+            ("Enter", "Save changes"),
+            ("Esc", "Cancel"),
+            ("Ctrl-c", "Commit or discard the edit, then save and quit"),
+        ],
+    ),
+    ("LEAVING HELP", &[("q / Q", "Quit help screen")]),
+];
+
+/// Renders the keybinding reference as plain text lines with aligned keys
+pub fn key_help_lines() -> Vec<String> {
+    // Flatten all key lengths to compute global max
+    let max_key_len = KEY_HELP
+        .iter()
+        .flat_map(|(_, lines)| lines.iter().map(|(k, _)| k.len()))
+        .max()
+        .unwrap_or(0);
+
+    let mut out = Vec::new();
+    for (title, lines) in KEY_HELP.iter() {
+        out.push(title.to_string());
+        for (key, desc) in lines.iter() {
+            let padded = format!("{:width$}", key, width = max_key_len);
+            out.push(format!("  {}  - {}", padded, desc));
+        }
+        out.push(String::new());
+    }
+    out
+}
@@ -1,455 +1,4660 @@
 use std::{
-    io, mem,
+    borrow::Cow,
+    cell::{Ref, RefCell},
+    cmp::Reverse,
+    collections::{HashMap, HashSet, VecDeque},
+    env, fmt, fs,
+    io::{self, Write},
+    iter, mem,
+    ops::Range,
     path::{Path, PathBuf},
-    time::Duration,
+    process, ptr,
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    time::{Duration, Instant, SystemTime},
 };
 
-use crossterm::event::KeyCode;
-use unicode_width::UnicodeWidthStr;
+use anyhow::Context;
+use crossterm::event::{Event, KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use crossterm::style::Color;
+use signal_hook::consts::{SIGINT, SIGTERM};
+use tick::file::{Backend, FileBackend, Line, TodoFile, check_writable, get_todos_dones, modified};
+use tick::item::{
+    Format, HEADING_MARK, Item, Kind, PRIORITIES, SUBTASK_INDENT, SUBTASK_MARK, clean_text,
+    days_between, dedupe, entry_source, entry_text, first_tag, has_tag, heading_text, is_date,
+    is_tag, parent_of, split_added, split_done, split_due, split_priority, stamp_added,
+    subtask_count, subtask_text, timestamp, today, with_done, with_due, with_priority, with_source,
+};
+use tick::text::{grapheme_width, split_to_fit, text_width, truncate_to_fit};
+use unicode_segmentation::UnicodeSegmentation;
 
-use crate::action::{Action, InsertAction, TabAction};
+use crate::action::{Action, InsertAction, TabAction, key_help_lines};
+use crate::cli::{StartAt, resolve_path};
+use crate::config::{Config, ListEnd, NumberAlign, Theme};
 use crate::helpers::{
-    clear_scr, get_key_event, get_todos_dones, goto, goto_begin, handle_term_size, init_scr,
-    save_to_file, split_to_fit, write_text,
+    FuzzyMatch, Highlight, append_line, copy_to_clipboard, fuzzy_match, get_events, goto,
+    grapheme_to_byte, handle_term_size, init_scr, is_transient, paste_from_clipboard, pop_grapheme,
+    print_lists, reset_scr, show_cursor, truncate_chars, word_end, word_start,
 };
-use crate::{globals, screen_guard::ScreenGuard, tab::Tab};
+use crate::lock::FileLock;
+use crate::state::UiState;
+use crate::{frame::Frame, globals, hooks, screen_guard::ScreenGuard, tab::Tab};
+
+/// Shown when a change is attempted with `--read-only`
+const READ_ONLY_MSG: &str = "Read-only, nothing can be changed";
+
+/// Wrapped items kept before the cache is dropped and rebuilt. Only items on
+/// screen are ever wrapped, so this is many screens' worth however long the
+/// lists get
+const WRAP_CACHE_LIMIT: usize = 4096;
+
+/// How often the file is checked for changes made by other programs
+const FILE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Undo entries kept before the oldest ones are dropped
+const UNDO_LIMIT: usize = 100;
+
+/// Narrower terminals show only the active tab, full width
+const MIN_SPLIT_COLS: u16 = 40;
+
+/// How long a pressed leader key, or `d`, waits for the rest of the binding
+const LEADER_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long a flashed status message stays when no key is pressed
+const STATUS_FLASH: Duration = Duration::from_secs(1);
+
+/// Screen text that only depends on the terminal size, rebuilt when it changes
+/// rather than on every frame
+struct StaticText {
+    size: (u16, u16),
+    help_lines: Vec<String>,
+}
+
+impl StaticText {
+    fn new(size: (u16, u16)) -> Self {
+        let cols = size.0 as usize;
+        // Title bar
+        let mut help_lines = vec!["=".repeat(cols), "HELP".into(), "=".repeat(cols)];
+        help_lines.push(String::new());
+        help_lines.extend(key_help_lines());
+
+        // Bottom border with the version right-aligned inside it
+        let version = format!(" v{} ", globals::VERSION);
+        let border_len = cols.saturating_sub(version.len() + 2);
+        help_lines.push(format!("{}{}==", "=".repeat(border_len), version));
+
+        Self { size, help_lines }
+    }
+}
+
+/// Numbers drawn in place of the checkboxes, cycled with `n`
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Numbers {
+    Off,
+    Absolute,
+    /// Distance from the cursor, the selected item keeping its own number
+    Relative,
+}
+
+impl Numbers {
+    fn next(self) -> Self {
+        match self {
+            Numbers::Off => Numbers::Absolute,
+            Numbers::Absolute => Numbers::Relative,
+            Numbers::Relative => Numbers::Off,
+        }
+    }
+}
+
+impl fmt::Display for Numbers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Numbers::Off => "off",
+            Numbers::Absolute => "absolute",
+            Numbers::Relative => "relative",
+        })
+    }
+}
+
+impl FromStr for Numbers {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Numbers::Off),
+            "absolute" => Ok(Numbers::Absolute),
+            "relative" => Ok(Numbers::Relative),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How a list was last sorted, sorted that way again when its file is
+/// reopened. Moving an item by hand goes back to the order items were added in
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+enum SortMode {
+    #[default]
+    Insertion,
+    Ascending,
+    Descending,
+    Priority,
+    /// Latest completion date first, for `dones_newest_first`
+    Completion,
+}
+
+impl fmt::Display for SortMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            SortMode::Insertion => "insertion",
+            SortMode::Ascending => "a-z",
+            SortMode::Descending => "z-a",
+            SortMode::Priority => "priority",
+            SortMode::Completion => "completion",
+        })
+    }
+}
+
+impl FromStr for SortMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "insertion" => Ok(SortMode::Insertion),
+            "a-z" => Ok(SortMode::Ascending),
+            "z-a" => Ok(SortMode::Descending),
+            "priority" => Ok(SortMode::Priority),
+            "completion" => Ok(SortMode::Completion),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A file opened alongside the first one. Its items are merged into the
+/// lists, marked with its number (see [`with_source`]), and saved back to it
+struct ExtraFile {
+    path: PathBuf,
+    /// Drawn in front of its items
+    label: String,
+    layout: Vec<Line>,
+    _lock: Option<FileLock>,
+}
+
+/// What the lists are narrowed down to
+enum Filter {
+    /// Items carrying this lowercase tag, without the `#`
+    Tag(String),
+    /// Todos due on this date or earlier, the day the filter was set
+    Due(String),
+}
+
+/// State of the lists before an undoable change
+struct Snapshot {
+    todos: Vec<String>,
+    dones: Vec<String>,
+    someday: Vec<String>,
+    archived_todos: Vec<String>,
+    archived_dones: Vec<String>,
+    todos_idx: usize,
+    dones_idx: usize,
+    someday_idx: usize,
+    curr_tab: Tab,
+}
 
 enum InsertMode {
     New,
     Edit(String),
 }
 
+/// Destructive actions waiting for the user's go-ahead
+enum Confirm {
+    QuitNoSave,
+    /// The file was modified by another program while there are unsaved edits
+    ExternalChange,
+    ClearList,
+    /// Quitting would save every list empty over a file that has items
+    SaveEmpty,
+}
+
+impl Confirm {
+    fn prompt(&self) -> &'static str {
+        match self {
+            Confirm::QuitNoSave => "Quit without saving?",
+            Confirm::ExternalChange => "File changed on disk, reload and drop your changes?",
+            Confirm::ClearList => "Delete every item shown in this tab?",
+            Confirm::SaveEmpty => "Saving leaves a file without any of its items, go ahead?",
+        }
+    }
+}
+
 enum Mode {
     Normal,
     Insert(InsertMode),
     Help,
+    Archived,
+    Confirm(Confirm),
+    /// Ex-style command line, holding what was typed after `:`
+    Command(String),
+    /// Query typed after `/`, matches in the current tab are underlined
+    Search(String),
+    /// Box over the lists with the whole text of the selected item, any key
+    /// closes it
+    FullText,
+    /// Box over the lists with an error that didn't end the session, any key
+    /// closes it
+    Error(String),
+    /// Items between this index and the cursor are selected
+    Visual(usize),
 }
 
 pub struct App {
     todos: Vec<String>,
     dones: Vec<String>,
+    someday: Vec<String>,
+    archived_todos: Vec<String>,
+    archived_dones: Vec<String>,
+    /// Lines of the file as loaded, so comments and blank lines are saved
+    /// back where they were
+    layout: Vec<Line>,
     file_path: PathBuf,
+    /// Modification time of the file when it was last loaded or saved
+    file_mtime: Option<SystemTime>,
+    last_file_check: Instant,
+    /// Changes were made since the last load or save
+    dirty: bool,
+    /// When the file was last saved, and the changes since, for autosaving
+    last_save: Instant,
+    edits_since_save: usize,
     todos_idx: usize,
     dones_idx: usize,
+    someday_idx: usize,
+    archived_idx: usize,
+    /// First item drawn of each list, keeping the selected one on screen
+    todos_scroll: usize,
+    dones_scroll: usize,
+    someday_scroll: usize,
+    archived_scroll: usize,
     curr_tab: Tab,
     mode: Mode,
-    show_number: bool,
+    show_number: Numbers,
+    /// Sorting of the todos, dones and someday lists, in that order
+    sort_modes: [SortMode; 3],
+    /// Items are cut to one row instead of wrapping
+    truncate: bool,
+    /// Only the active tab is drawn, across the whole width
+    single_column: bool,
+    /// Columns and rows of the terminal, kept up to date by resize events
+    term_size: (u16, u16),
+    /// Last screen drawn, compared against to only redraw what changed
+    last_frame: Option<Frame>,
+    /// Query repeated by `n` / `N`
+    last_search: Option<String>,
+    filter: Option<Filter>,
+    /// Items whose subtasks are hidden
+    collapsed: HashSet<String>,
+    status_msg: Option<String>,
+    /// Flashed message and when it goes away, unless a key clears it first
+    status_expiry: Option<(Instant, String)>,
+    registers: HashMap<char, Vec<String>>,
+    pending_count: Option<usize>,
+    active_register: Option<char>,
+    awaiting_register: bool,
+    /// Char offset of the cursor in the item being inserted or edited
+    edit_cursor: usize,
+    /// When the leader key was pressed, while waiting for the next key
+    leader_since: Option<Instant>,
+    /// When `d` was pressed and the count typed before it, while it waits for
+    /// a motion
+    pending_delete: Option<(Instant, Option<usize>)>,
+    quit_prompt: bool,
+    dragging: bool,
+    /// The dragged item has moved, so the drag already has an undo entry
+    drag_moved: bool,
+    undo_stack: VecDeque<Snapshot>,
+    redo_stack: Vec<Snapshot>,
+    /// Wrapped lines by (line, width, continuation offset)
+    wrap_cache: RefCell<HashMap<(String, usize, usize), Vec<String>>>,
+    static_text: RefCell<StaticText>,
+    /// Failures reported by hooks running in the background
+    hook_reports: (Sender<String>, Receiver<String>),
+    config: Config,
+    /// Held until the app is dropped, `None` if the lock couldn't be written
+    _lock: Option<FileLock>,
+    /// Files opened after the first, numbered from 1 in their items
+    extra_files: Vec<ExtraFile>,
 }
 
 /// Constructor / Entry Point
 impl App {
-    pub fn new<P>(file_path: P) -> anyhow::Result<Self>
+    pub fn new<P>(file_path: P, config: Config) -> anyhow::Result<Self>
     where
         P: AsRef<Path>,
     {
-        let (todos, dones) = get_todos_dones(&file_path)?;
-        let s = Self {
-            todos,
-            dones,
-            file_path: file_path.as_ref().to_path_buf(),
+        // A viewer can sit next to the session editing the file
+        let lock = if config.read_only {
+            None
+        } else {
+            FileLock::acquire(file_path.as_ref())?
+        };
+        let loaded = FileBackend::new(file_path.as_ref()).load(config.format, &config.prefixes)?;
+        // Warn now rather than have the session fail to save at quit time
+        let unwritable = check_writable(&file_path).err();
+        let ui_state = UiState::load();
+
+        let mut s = Self::with_lists(file_path.as_ref().to_path_buf(), loaded, config);
+        s._lock = lock;
+        s.file_mtime = modified(&file_path);
+        s.curr_tab = ui_state.get("tab").unwrap_or(Tab::Todos);
+        s.show_number = ui_state.get("show_number").unwrap_or(Numbers::Off);
+        // The cursors only mean something in the file they were left in, and
+        // are clamped in case it changed since
+        let cursor_file = ui_state.get::<PathBuf>("cursor_file");
+        if fs::canonicalize(&file_path).is_ok_and(|path| cursor_file == Some(path)) {
+            s.todos_idx = ui_state.get("todos_idx").unwrap_or(0);
+            s.dones_idx = ui_state.get("dones_idx").unwrap_or(0);
+            s.someday_idx = ui_state.get("someday_idx").unwrap_or(0);
+            s.clamp_indexes();
+        }
+        if let Ok(path) = fs::canonicalize(&file_path) {
+            s.restore_sort_modes(&ui_state, &path);
+        }
+        if let Some(e) = unwritable
+            && !s.config.read_only
+        {
+            s.status_msg = Some(format!(
+                "Can't save: {e}. Quitting saves ./{}.recovered instead",
+                file_path.as_ref().file_name().unwrap_or_default().display()
+            ));
+        }
+        Ok(s)
+    }
+
+    /// Sets up a session over lists already loaded, touching neither the file,
+    /// the terminal nor the saved UI state. Every key and action can be fed to
+    /// it without a screen; only saving and the actions that run programs
+    /// (hooks, clipboard, `$EDITOR`) reach outside
+    pub(crate) fn with_lists(file_path: PathBuf, loaded: TodoFile, config: Config) -> Self {
+        let status_msg = loaded.load_msg();
+        // Lines with a legacy prefix aren't in the file the way they'll be saved
+        let dirty = loaded.migrated > 0;
+        Self {
+            todos: loaded.todos,
+            dones: loaded.dones,
+            someday: loaded.someday,
+            archived_todos: loaded.archived_todos,
+            archived_dones: loaded.archived_dones,
+            layout: loaded.layout,
+            file_path,
+            file_mtime: None,
+            last_file_check: Instant::now(),
+            dirty,
+            last_save: Instant::now(),
+            edits_since_save: 0,
             todos_idx: 0,
             dones_idx: 0,
+            someday_idx: 0,
+            archived_idx: 0,
+            todos_scroll: 0,
+            dones_scroll: 0,
+            someday_scroll: 0,
+            archived_scroll: 0,
             curr_tab: Tab::Todos,
             mode: Mode::Normal,
-            show_number: false,
+            show_number: Numbers::Off,
+            sort_modes: [SortMode::Insertion; 3],
+            last_search: None,
+            filter: None,
+            collapsed: HashSet::new(),
+            term_size: (0, 0),
+            truncate: config.truncate,
+            single_column: false,
+            last_frame: None,
+            status_msg,
+            status_expiry: None,
+            registers: HashMap::new(),
+            pending_count: None,
+            active_register: None,
+            awaiting_register: false,
+            edit_cursor: 0,
+            leader_since: None,
+            pending_delete: None,
+            quit_prompt: false,
+            dragging: false,
+            drag_moved: false,
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            wrap_cache: RefCell::default(),
+            static_text: RefCell::new(StaticText::new((0, 0))),
+            hook_reports: mpsc::channel(),
+            config,
+            _lock: None,
+            extra_files: Vec::new(),
+        }
+    }
+
+    /// Opens another file alongside the current one: its items join the
+    /// lists, labeled with its name, and are saved back to it. Up to nine
+    /// files can be opened this way
+    pub fn open_extra(&mut self, file_path: &Path) -> anyhow::Result<()> {
+        let open = iter::once(&self.file_path).chain(self.extra_files.iter().map(|f| &f.path));
+        if open.into_iter().any(|path| path == file_path) {
+            anyhow::bail!("{} is given twice", file_path.display());
+        }
+        if self.extra_files.len() == 9 {
+            anyhow::bail!("At most 10 files can be opened together");
+        }
+
+        let loaded = get_todos_dones(file_path, self.config.format, &self.config.prefixes)?;
+        let lock = if self.config.read_only {
+            None
+        } else {
+            FileLock::acquire(file_path)?
         };
-        Ok(s)
+        let source = self.extra_files.len() + 1;
+        let marked = |items: Vec<String>| items.into_iter().map(move |e| with_source(&e, source));
+        self.todos.extend(marked(loaded.todos));
+        self.dones.extend(marked(loaded.dones));
+        self.someday.extend(marked(loaded.someday));
+        self.archived_todos.extend(marked(loaded.archived_todos));
+        self.archived_dones.extend(marked(loaded.archived_dones));
+        self.dirty |= loaded.migrated > 0;
+
+        let label = file_path.file_stem().unwrap_or_default();
+        self.extra_files.push(ExtraFile {
+            path: file_path.to_path_buf(),
+            label: label.to_string_lossy().into_owned(),
+            layout: loaded.layout,
+            _lock: lock,
+        });
+        Ok(())
+    }
+
+    /// Puts the cursor on the item asked for on the command line. Positions
+    /// past the end land on the last item
+    pub fn start_at(&mut self, start_at: StartAt) {
+        match start_at {
+            StartAt::Position(pos) => {
+                self.curr_tab = Tab::Todos;
+                self.goto_list_pos(pos.saturating_sub(1));
+            }
+            StartAt::Text(text) => {
+                let find = |items: &[String]| items.iter().position(|item| item.contains(&text));
+                if let Some(idx) = find(&self.todos) {
+                    self.curr_tab = Tab::Todos;
+                    self.todos_idx = idx;
+                } else if let Some(idx) = find(&self.dones) {
+                    self.curr_tab = Tab::Dones;
+                    self.dones_idx = idx;
+                } else {
+                    self.status_msg = Some(format!("No item contains {:?}", text));
+                }
+            }
+        }
     }
 
-    pub fn run(&mut self) -> anyhow::Result<()> {
+    /// Runs the UI until the user quits, returning whether they quit with the
+    /// usual save rather than without saving
+    pub fn run(&mut self) -> anyhow::Result<bool> {
+        let mut should_save = true;
         if let Err(e) = self.main_loop() {
             match e.to_string().as_str() {
-                globals::NO_SAVE => return Ok(()),
+                globals::NO_SAVE => should_save = false,
                 globals::BREAK => {}
                 _ => Err(e)?,
             }
         }
-        save_to_file(&self.file_path, &self.todos, &self.dones)?;
-        Ok(())
+
+        // Saving trims and reformats every item line, so todo.txt files, often
+        // shared with other tools, are only rewritten when something changed
+        let unchanged_todo_txt = self.config.format == Format::TodoTxt && !self.dirty;
+        if should_save && !unchanged_todo_txt && !self.config.read_only {
+            match self.save() {
+                // The log already has it
+                Ok(()) if self.config.save_log => {}
+                Ok(()) => println!("Saved state to {}", self.file_path.display()),
+                Err(e) => {
+                    eprintln!("Couldn't save to {}: {e}", self.file_path.display());
+                    let fallback = self.save_fallback()?;
+                    println!("Saved state to {} instead", fallback.display());
+                }
+            }
+        }
+
+        let mut ui_state = UiState::load();
+        ui_state.set("tab", self.curr_tab);
+        ui_state.set("show_number", self.show_number);
+        if let Ok(path) = fs::canonicalize(&self.file_path) {
+            ui_state.set("cursor_file", path.display());
+            ui_state.set("todos_idx", self.todos_idx);
+            ui_state.set("dones_idx", self.dones_idx);
+            ui_state.set("someday_idx", self.someday_idx);
+            self.store_sort_modes(&mut ui_state, &path);
+        }
+        if let Err(e) = ui_state.save() {
+            eprintln!("Couldn't save the UI state: {e}");
+        }
+
+        // The alternate screen is already gone, so this lands in the scrollback
+        if self.config.print_on_exit {
+            let theme = &self.config.theme;
+            let indicators = (theme.todo_indicator.as_str(), theme.done_indicator.as_str());
+            print_lists(&self.todos, &self.dones, indicators);
+        }
+        Ok(should_save)
     }
 }
 
 /// Main loop / Screen Drawing
 impl App {
     fn main_loop(&mut self) -> anyhow::Result<()> {
-        let _guard = ScreenGuard {};
-        let mut term_size = Default::default();
-        init_scr()?;
+        let _guard = ScreenGuard {
+            inline: self.config.no_alt_screen,
+        };
+        init_scr(self.config.mouse, self.config.no_alt_screen)?;
+        handle_term_size(&mut self.term_size)?;
+
+        // Raw mode keeps Ctrl-C from raising SIGINT, so these only come from
+        // outside, like `kill` or a shutdown. Quit the usual way so the lists are saved
+        let terminate = Arc::new(AtomicBool::new(false));
+        for signal in [SIGINT, SIGTERM] {
+            signal_hook::flag::register(signal, Arc::clone(&terminate))?;
+        }
+
+        // The screen is only redrawn when something may have changed on it
+        let mut redraw = true;
         loop {
+            if terminate.load(Ordering::Relaxed) {
+                // There's no one to ask whether emptying the file was meant
+                if self.empties_file() {
+                    anyhow::bail!(globals::NO_SAVE);
+                }
+                anyhow::bail!(globals::BREAK);
+            }
             self.clamp_indexes();
-            handle_term_size(&mut term_size)?;
-            clear_scr()?;
-            goto_begin()?;
-            self.write_screen(term_size)?;
-            self.write_status(term_size)?;
-
-            let mid_scr = term_size.0 / 2;
-            match self.curr_tab {
-                Tab::Todos => goto(0, self.todos_idx as u16 + 1)?,
-                Tab::Dones => goto(mid_scr, self.dones_idx as u16 + 1)?,
-            }
-
-            if let Some(event) = get_key_event(Duration::from_millis(1000 / 60))? {
-                match self.mode {
-                    Mode::Normal => {
-                        if let Ok(action) = Action::try_from(event) {
-                            self.execute_action(action)?;
-                        }
+            if let Ok(report) = self.hook_reports.1.try_recv() {
+                self.status_msg = Some(report);
+                redraw = true;
+            }
+            if matches!(self.mode, Mode::Normal)
+                && self.last_file_check.elapsed() >= FILE_CHECK_INTERVAL
+            {
+                self.last_file_check = Instant::now();
+                let changed = self.check_file_changed();
+                // An error box is shown instead when it couldn't be reloaded
+                redraw |= self.recover(changed)?.unwrap_or(true);
+            }
+            if matches!(self.mode, Mode::Normal) && self.autosave_due() {
+                redraw |= self.autosave();
+            }
+            // Unless another message took its place meanwhile
+            if let Some((_, msg)) = self
+                .status_expiry
+                .take_if(|(until, _)| Instant::now() >= *until)
+                && self.status_msg.as_ref() == Some(&msg)
+            {
+                self.status_msg = None;
+                redraw = true;
+            }
+            if self
+                .leader_since
+                .is_some_and(|since| since.elapsed() > LEADER_TIMEOUT)
+            {
+                self.leader_since = None;
+                redraw = true;
+            }
+            if self
+                .pending_delete
+                .is_some_and(|(since, _)| since.elapsed() > LEADER_TIMEOUT)
+            {
+                self.pending_delete = None;
+                self.pending_count = None;
+                redraw = true;
+            }
+            if redraw {
+                if let Err(err) = self.draw_frame(self.term_size) {
+                    if !is_transient(&err) {
+                        return Err(err.into());
                     }
-                    Mode::Insert(_) => {
-                        if let Ok(action) = InsertAction::try_from(event) {
-                            self.handle_insert_mode(action);
-                        }
+                    // A momentary write failure on a flaky terminal shouldn't end the
+                    // session, redraw once and only give up if it fails again
+                    self.status_msg = Some(format!("Terminal error, screen redrawn: {err}"));
+                    self.draw_frame(self.term_size)?;
+                }
+                redraw = false;
+            }
+
+            // Everything queued up since the last frame is applied in order,
+            // typed characters included, and drawn once
+            for event in get_events(self.next_timeout())? {
+                match event {
+                    Event::Key(event) => {
+                        let handled = self.handle_key_event(event);
+                        self.recover(handled)?;
                     }
-                    Mode::Help => {
-                        if let Ok(action) = Action::try_from(event) {
-                            self.handle_help_mode(action);
-                        }
+                    Event::Mouse(event) => self.handle_mouse_event(event, self.term_size),
+                    Event::Resize(cols, rows) => self.term_size = (cols, rows),
+                    Event::Paste(text) => {
+                        let handled = self.handle_paste_event(&text);
+                        self.recover(handled)?;
                     }
+                    _ => continue,
                 }
+                self.clamp_indexes();
+                redraw = true;
             }
         }
     }
 
-    fn write_screen(&self, term_size: (u16, u16)) -> io::Result<()> {
-        if matches!(self.mode, Mode::Help) {
-            self.write_help_screen(term_size)?;
-        } else {
-            self.write_header(term_size)?;
-            self.write_todos_dones(term_size)?;
-        }
-        Ok(())
-    }
-
-    fn write_header(&self, term_size: (u16, u16)) -> io::Result<()> {
-        let col_mid = term_size.0 / 2;
-        let is_tab_todo = matches!(self.curr_tab, Tab::Todos);
+    /// How long to wait for input before the next timer is due. Input wakes
+    /// the loop right away, `poll_interval` only bounds how late hook reports
+    /// and signals are noticed
+    fn next_timeout(&self) -> Duration {
+        let normal = matches!(self.mode, Mode::Normal);
+        let autosave = self
+            .config
+            .autosave_interval
+            .filter(|_| normal && self.dirty && !self.config.read_only)
+            .map(|interval| self.last_save + interval);
+        let deadlines = [
+            normal.then(|| self.last_file_check + FILE_CHECK_INTERVAL),
+            autosave,
+            self.status_expiry.as_ref().map(|(until, _)| *until),
+            self.leader_since.map(|since| since + LEADER_TIMEOUT),
+            self.pending_delete.map(|(since, _)| since + LEADER_TIMEOUT),
+        ];
 
-        write_text("TODO", is_tab_todo)?;
-        write_text(&" ".repeat(col_mid as usize - 4), false)?;
-        write_text("DONE\r\n", !is_tab_todo)?;
-        Ok(())
+        let now = Instant::now();
+        deadlines
+            .into_iter()
+            .flatten()
+            .map(|deadline| deadline.saturating_duration_since(now))
+            .fold(self.config.poll_interval.0, Duration::min)
     }
 
-    fn write_status(&self, term_size: (u16, u16)) -> io::Result<()> {
-        let (_cols, rows) = term_size;
-        goto(0, rows - 1)?;
-        let txt = match self.mode {
-            Mode::Normal => "NORMAL",
-            Mode::Insert(InsertMode::New) => "INSERT",
-            Mode::Insert(InsertMode::Edit(_)) => "EDIT",
-            Mode::Help => "HELP",
-        };
-
-        write_text(txt, true)
+    /// Keeps the session going after an error from handling input, showing it
+    /// in a box until a key is pressed. Quitting and errors of the terminal
+    /// itself, which come from drawing and reading events, still end it
+    fn recover<T>(&mut self, result: anyhow::Result<T>) -> anyhow::Result<Option<T>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if matches!(e.to_string().as_str(), globals::BREAK | globals::NO_SAVE) => Err(e),
+            Err(e) => {
+                self.mode = Mode::Error(format!("{e:#}"));
+                Ok(None)
+            }
+        }
     }
 
-    fn write_help_screen(&self, term_size: (u16, u16)) -> io::Result<()> {
-        let (cols, _) = term_size;
-
-        // Helper to print a full line with newline
-        let println = |s: &str| -> io::Result<()> {
-            write_text(s, false)?;
-            write_text("\r\n", false)?;
-            Ok(())
-        };
-
-        // Title bar
-        println(&"=".repeat(cols as usize))?;
-        println("HELP")?;
-        println(&"=".repeat(cols as usize))?;
-        println("")?;
-
-        let sections: &[(&str, &[(&str, &str)])] = &[
-            (
-                "ACTIONS",
-                &[
-                    ("f1", "Show this screen"),
-                    ("i / o", "Insert item above / below"),
-                    ("e", "Edit item under cursor"),
-                    ("J / K", "Move item under cursor down / up"),
-                    ("q", "Save and quit"),
-                    ("Q", "Quit without saving"),
-                ],
-            ),
-            (
-                "MOVEMENT",
-                &[
-                    ("j / k", "Move cursor down / up"),
-                    ("g / G", "Jump to beginning / end"),
-                    ("Tab", "Toggle Tab"),
-                    ("<- / ->", "Change to todo/done tab"),
-                ],
-            ),
-            (
-                "INSERT / EDIT MODE",
-                &[
-                    ("(type normally)", "Edit text"),
-                    ("Enter", "Save changes"),
-                    ("Esc", "Cancel"),
-                ],
-            ),
-            ("LEAVING HELP", &[("q / Q", "Quit help screen")]),
-        ];
+    fn handle_key_event(&mut self, event: KeyEvent) -> anyhow::Result<()> {
+        // Any keypress dismisses the current status message
+        self.status_msg = None;
+        match self.mode {
+            Mode::Normal if self.awaiting_register => {
+                self.awaiting_register = false;
+                if let KeyCode::Char(name) = event.code {
+                    self.active_register = Some(name);
+                }
+            }
+            Mode::Normal if self.leader_since.is_some() => {
+                self.leader_since = None;
+                if let Some(action) = Action::from_leader(event) {
+                    self.execute_action(action)?;
+                }
+                self.pending_count = None;
+            }
+            Mode::Normal if self.pending_delete.is_some() => {
+                if self.push_count_digit(event.code) {
+                    return Ok(());
+                }
+                let Some((_, count)) = self.pending_delete.take() else {
+                    unreachable!("Checked by the guard");
+                };
+                // Counts before and after `d` multiply, like in vim
+                let count = count.unwrap_or(1) * self.pending_count.take().unwrap_or(1);
+                let motion = Action::from_key(event, &self.config.function_keys);
+                self.apply_delete_operator(count, motion)?;
+            }
+            Mode::Normal => {
+                if self.push_count_digit(event.code) {
+                    return Ok(());
+                }
 
-        // Flatten all key lengths to compute global max
-        let max_key_len = sections
-            .iter()
-            .flat_map(|(_, lines)| lines.iter().map(|(k, _)| k.len()))
-            .max()
-            .unwrap_or(0);
+                if event.code == KeyCode::Char(self.config.leader.0) {
+                    self.leader_since = Some(Instant::now());
+                    return Ok(());
+                }
 
-        for (title, lines) in sections.iter() {
-            println(title)?;
-            for (key, desc) in lines.iter() {
-                let padded = format!("{:width$}", key, width = max_key_len);
-                println(&format!("  {}  - {}", padded, desc))?;
+                if let Ok(action) = Action::from_key(event, &self.config.function_keys) {
+                    self.execute_action(action)?;
+                }
+                self.pending_count = None;
+            }
+            Mode::Insert(_) if self.quit_prompt => {
+                self.quit_prompt = false;
+                match event.code {
+                    KeyCode::Char('c') => {
+                        self.disable_insert_mode();
+                        anyhow::bail!(globals::BREAK);
+                    }
+                    KeyCode::Char('d') => {
+                        self.handle_insert_mode(InsertAction::Cancel);
+                        anyhow::bail!(globals::BREAK);
+                    }
+                    _ => {}
+                }
+            }
+            Mode::Insert(_) => {
+                if let Ok(action) = InsertAction::try_from(event) {
+                    self.handle_insert_mode(action);
+                }
+            }
+            Mode::Help => {
+                if let Ok(action) = Action::try_from(event) {
+                    self.handle_help_mode(action);
+                }
+            }
+            Mode::FullText | Mode::Error(_) => self.mode = Mode::Normal,
+            Mode::Visual(_) if event.code == KeyCode::Esc => self.mode = Mode::Normal,
+            Mode::Visual(_) => {
+                if let Ok(action) = Action::from_key(event, &self.config.function_keys) {
+                    self.handle_visual_mode(action);
+                }
+            }
+            Mode::Archived => {
+                if let Ok(action) = Action::try_from(event) {
+                    self.handle_archived_mode(action);
+                }
+            }
+            Mode::Confirm(_) => self.handle_confirm_mode(event)?,
+            Mode::Command(_) => {
+                if let Ok(action) = InsertAction::try_from(event) {
+                    self.handle_command_mode(action)?;
+                }
+            }
+            Mode::Search(_) => {
+                if let Ok(action) = InsertAction::try_from(event) {
+                    self.handle_search_mode(action);
+                }
             }
-            println("")?;
         }
-
-        println(&"=".repeat(cols as usize))?;
         Ok(())
     }
 
-    fn write_todos_dones(&self, term_size: (u16, u16)) -> io::Result<()> {
-        let (cols, _) = term_size;
-        let col_mid = cols / 2;
-
-        let draw_items = |items: &[String],
-                          line_begin: &str,
-                          is_active_tab: bool,
-                          selected_idx: usize,
-                          col_offset: u16|
-         -> io::Result<()> {
-            let mut current_line = 1;
-            for (idx, item) in items.iter().enumerate() {
-                let should_highlight = is_active_tab && idx == selected_idx;
+    fn draw_frame(&mut self, term_size: (u16, u16)) -> io::Result<()> {
+        self.scroll_into_view(term_size);
+        let mut frame = Frame::new(term_size, self.config.plain);
+        self.write_screen(&mut frame, term_size);
+        self.write_hints(&mut frame, term_size);
+        self.write_status(&mut frame, term_size);
 
-                let num_width = items.len().to_string().len(); // width of the largest number
+        // Losing the last frame, after a failed write for one, redraws everything
+        let previous = self.last_frame.take();
+        frame.render(previous.as_ref())?;
+        self.last_frame = Some(frame);
 
-                let line_label = if self.show_number {
-                    format!("{:>width$}.", idx + 1, width = num_width)
-                } else {
-                    String::from(line_begin)
-                };
+        // The cursor is only visible while typing
+        show_cursor(matches!(
+            self.mode,
+            Mode::Insert(_) | Mode::Command(_) | Mode::Search(_)
+        ))?;
+        let (col, row) = match &self.mode {
+            Mode::Command(buf) | Mode::Search(buf) => (1 + text_width(buf) as u16, term_size.1 - 1),
+            _ => self.cursor_position(term_size.0),
+        };
+        goto(col, row)?;
+        io::stdout().flush()
+    }
 
-                let full_line = format!("{} {}", line_label, item);
-                let offset = line_begin.width() + 1;
-                let (first_line, rest_lines) = split_to_fit(
-                    &full_line,
-                    col_mid as usize - if col_offset > 0 { 0 } else { 1 },
-                    offset,
-                );
+    fn write_screen(&self, frame: &mut Frame, term_size: (u16, u16)) {
+        if matches!(self.mode, Mode::Help) {
+            self.write_help_screen(frame, term_size);
+        } else {
+            self.write_header(frame, term_size);
+            self.write_todos_dones(frame, term_size);
+            self.write_empty_hint(frame, term_size);
+        }
+        match &self.mode {
+            Mode::FullText => self.write_full_text(frame, term_size),
+            Mode::Error(msg) => Self::write_box(frame, term_size, &format!("Error: {msg}")),
+            _ => {}
+        }
+    }
 
-                // Start at the first line for this item
+    /// Tells how to start when there's nothing in either list, like in a new file
+    fn write_empty_hint(&self, frame: &mut Frame, term_size: (u16, u16)) {
+        let in_columns = matches!(self.curr_tab, Tab::Todos | Tab::Dones);
+        let empty = self.todos.is_empty() && self.dones.is_empty();
+        if !matches!(self.mode, Mode::Normal) || !in_columns || !empty {
+            return;
+        }
 
-                // Draw first line
-                goto(col_offset, current_line)?;
-                write_text(first_line, should_highlight)?;
-                current_line += 1;
+        let (cols, rows) = term_size;
+        let hint = truncate_to_fit("Press i to add your first task", cols as usize);
+        frame.goto(cols.saturating_sub(text_width(&hint) as u16) / 2, rows / 2);
+        frame.set_dim(true);
+        frame.write_text(&hint, false);
+        frame.set_dim(false);
+    }
 
-                let padding = " ".repeat(line_begin.width() + 1);
-                for line in rest_lines {
-                    goto(col_offset, current_line)?;
-                    write_text(&format!("{}{}", padding, line), should_highlight)?;
-                    current_line += 1;
-                }
-            }
-            Ok(())
+    /// Box with the selected item's whole text
+    fn write_full_text(&self, frame: &mut Frame, term_size: (u16, u16)) {
+        let Some(entry) = self.get_current_buffer() else {
+            return;
         };
-
-        draw_items(
-            &self.todos,
-            globals::TODO_INDICATOR,
-            matches!(self.curr_tab, Tab::Todos),
-            self.todos_idx,
-            0,
-        )?;
-        draw_items(
-            &self.dones,
-            globals::DONE_INDICATOR,
-            matches!(self.curr_tab, Tab::Dones),
-            self.dones_idx,
-            col_mid,
-        )?;
-        Ok(())
+        Self::write_box(frame, term_size, entry_text(entry));
     }
-}
 
-/// Actions / Mode Handling
-impl App {
-    fn execute_action(&mut self, action: Action) -> anyhow::Result<()> {
-        match action {
-            Action::Enter => self.handle_enter_press(),
-            Action::SwitchTab(tab) => match tab {
-                TabAction::Toggle => self.curr_tab = self.curr_tab.toggle(),
-                TabAction::Left => self.curr_tab = Tab::Todos,
-                TabAction::Right => self.curr_tab = Tab::Dones,
-            },
-            Action::Insert(direction) => self.start_insert_mode(direction),
-            Action::Edit => self.start_edit_mode(),
-            Action::MoveCursor(direction) => self.handle_cursor_move(direction),
-            Action::MoveItem(direction) => self.handle_move_item(direction),
-            Action::GotoBegin => self.goto_list_pos(0),
-            Action::GotoEnd => self.goto_list_pos(usize::MAX),
-            Action::Delete => self.handle_delete(),
-            Action::SaveQuit => anyhow::bail!(globals::BREAK),
-            Action::NoSaveQuit => anyhow::bail!(globals::NO_SAVE),
-            Action::ShowHelp => self.mode = Mode::Help,
-            Action::ShowNumber => self.show_number = !self.show_number,
-        }
+    /// Centered box with `text` wrapped inside, framed like the help screen
+    fn write_box(frame: &mut Frame, term_size: (u16, u16), text: &str) {
+        let (cols, rows) = term_size;
+        let width = (cols as usize).saturating_sub(4).min(60);
+        // One column of padding on each side of the text
+        let inner = width.saturating_sub(2).max(1);
+        let (first, rest) = split_to_fit(text, inner, 0);
+        let mut lines: Vec<_> = [first].into_iter().chain(rest).collect();
+        // Keep the borders and the status line on screen
+        lines.truncate((rows as usize).saturating_sub(3).max(1));
 
-        Ok(())
+        let border = "=".repeat(width);
+        let top = (rows.saturating_sub(lines.len() as u16 + 2)) / 2;
+        let left = (cols.saturating_sub(width as u16)) / 2;
+        frame.goto(left, top);
+        frame.write_text(&border, false);
+        for (i, line) in lines.iter().enumerate() {
+            let fill = inner.saturating_sub(text_width(line));
+            frame.goto(left, top + 1 + i as u16);
+            frame.write_text(&format!(" {}{} ", line, " ".repeat(fill)), false);
+        }
+        frame.goto(left, top + 1 + lines.len() as u16);
+        frame.write_text(&border, false);
     }
 
-    fn handle_enter_press(&mut self) {
-        match self.curr_tab {
-            Tab::Todos => {
-                if self.todos.is_empty() {
-                    return;
-                }
-                let value = self.todos.remove(self.todos_idx);
-                self.dones.push(value);
-            }
-            Tab::Dones => {
-                if self.dones.is_empty() {
-                    return;
-                }
-                let value = self.dones.remove(self.dones_idx);
-                self.todos.push(value);
-            }
+    fn write_header(&self, frame: &mut Frame, term_size: (u16, u16)) {
+        let label = |name: &str, tab: Tab| format!("{} ({})", name, self.item_count(tab));
+        let active = |is_active: bool| is_active.then_some(self.config.theme.header);
+        if matches!(self.curr_tab, Tab::Someday) {
+            return frame.write_highlighted(&label("SOMEDAY", Tab::Someday), active(true));
         }
-    }
 
-    fn handle_cursor_move(&mut self, direction: KeyCode) {
-        let idx = match self.curr_tab {
-            Tab::Todos => &mut self.todos_idx,
-            Tab::Dones => &mut self.dones_idx,
+        let header = |tab: Tab| match tab {
+            Tab::Dones => label("DONE", Tab::Dones),
+            _ => label("TODO", Tab::Todos),
         };
-        match direction {
-            KeyCode::Down => *idx += 1,
-            KeyCode::Up => *idx = idx.saturating_sub(1),
-            _ => unreachable!("This spot should't be reachable"),
+        // Without styling the active header is told apart by brackets
+        let header = |tab: Tab| match header(tab) {
+            label if self.config.plain && self.curr_tab == tab => format!("[{label}]"),
+            label => label,
         };
+        let [left, right] = self.column_tabs();
+
+        frame.write_highlighted(&header(left), active(self.curr_tab == left));
+        // The right header sits against the right edge, balancing the left one
+        let right_label = header(right);
+        frame.goto(
+            term_size.0.saturating_sub(text_width(&right_label) as u16),
+            0,
+        );
+        frame.write_highlighted(&right_label, active(self.curr_tab == right));
     }
 
-    fn handle_delete(&mut self) {
-        let (target_vec, idx) = match self.curr_tab {
-            Tab::Todos => (&mut self.todos, &self.todos_idx),
-            Tab::Dones => (&mut self.dones, &self.dones_idx),
-        };
+    /// Items in the column of `tab`, headings left out
+    fn item_count(&self, tab: Tab) -> usize {
+        let (items, _, _) = self.column(tab);
+        items
+            .iter()
+            .filter(|entry| heading_text(entry).is_none())
+            .count()
+    }
 
-        if target_vec.is_empty() {
+    /// Share of the todos and dones that are done, `None` with no items
+    fn progress(&self) -> Option<usize> {
+        let dones = self.item_count(Tab::Dones);
+        let total = self.item_count(Tab::Todos) + dones;
+        (total > 0).then(|| dones * 100 / total)
+    }
+
+    /// Keys worth knowing in the current mode, on the row above the status line
+    fn write_hints(&self, frame: &mut Frame, term_size: (u16, u16)) {
+        let (cols, rows) = term_size;
+        if self.config.hide_key_hints || rows < 3 {
             return;
         }
+        let hints = match &self.mode {
+            Mode::Normal => "i:insert e:edit d:delete F1:help q:quit",
+            Mode::Insert(_) => "Enter:save Esc:cancel",
+            Mode::Help => "q:close",
+            Mode::FullText | Mode::Error(_) => "any key:close",
+            Mode::Visual(_) => "j/k:select d:delete y:yank Esc:back",
+            Mode::Archived => "a:restore q:back",
+            Mode::Command(_) => "Enter:run Esc:cancel",
+            Mode::Search(_) => "Enter:jump Esc:cancel",
+            // The prompt on the status line names its keys
+            Mode::Confirm(_) => return,
+        };
 
-        target_vec.remove(*idx);
+        frame.goto(0, rows - 2);
+        frame.set_dim(true);
+        frame.write_text(
+            &truncate_to_fit(hints, cols.saturating_sub(1) as usize),
+            false,
+        );
+        frame.set_dim(false);
     }
 
-    fn handle_move_item(&mut self, direction: KeyCode) {
-        let (vec, idx) = match self.curr_tab {
-            Tab::Todos => (&mut self.todos, &mut self.todos_idx),
-            Tab::Dones => (&mut self.dones, &mut self.dones_idx),
+    /// Rows under the lists, taken by the status line and the key hints
+    fn bottom_rows(&self) -> u16 {
+        if self.config.hide_key_hints { 1 } else { 2 }
+    }
+
+    fn write_status(&self, frame: &mut Frame, term_size: (u16, u16)) {
+        let (cols, rows) = term_size;
+        frame.goto(0, rows - 1);
+        let txt = match &self.mode {
+            Mode::Normal => "NORMAL",
+            Mode::Insert(InsertMode::New) => "INSERT",
+            Mode::Insert(InsertMode::Edit(_)) => "EDIT",
+            Mode::Help => "HELP",
+            Mode::FullText => "VIEW",
+            Mode::Error(_) => "ERROR",
+            Mode::Visual(_) => "VISUAL",
+            Mode::Archived => "ARCHIVED",
+            Mode::Confirm(_) => "CONFIRM",
+            // The command line takes the whole row
+            Mode::Command(buf) => return frame.write_text(&format!(":{buf}"), false),
+            Mode::Search(buf) => return frame.write_text(&format!("/{buf}"), false),
         };
 
-        if vec.is_empty() {
-            return;
+        frame.write_text(txt, true);
+        let mut used = txt.len();
+
+        if self.config.read_only {
+            used += " RO".len();
+            frame.write_text(" RO", false);
         }
 
-        let idx_val = *idx;
+        if let Some(filter) = &self.filter {
+            let filter = match filter {
+                Filter::Tag(tag) => format!(" #{tag}"),
+                Filter::Due(_) => " due".to_string(),
+            };
+            used += text_width(&filter);
+            frame.write_text(&filter, false);
+        }
 
-        let new_idx = match direction {
-            KeyCode::Down => (idx_val + 1).min(vec.len() - 1),
-            KeyCode::Up => idx_val.saturating_sub(1),
-            _ => unreachable!(),
+        let flags = self.status_flags();
+        if !flags.is_empty() {
+            let flags = format!(" [{flags}]");
+            used += flags.len();
+            frame.write_text(&flags, false);
+        }
+
+        // Keys typed so far of an unfinished binding, the cursor's position in
+        // the list, then the progress, go in the right corner
+        let mut corner = self.pending_keys();
+        // Counting only the items the tag filter shows
+        let (items, _, idx) = self.column(self.curr_tab);
+        let shown = |entries: &[String]| entries.iter().filter(|e| !self.is_hidden(e)).count();
+        let total = shown(items);
+        let position = (total > 0).then(|| format!("{}/{}", shown(&items[..idx]) + 1, total));
+        let progress = self.progress().map(|percent| format!("{percent}% done"));
+        for part in [position, progress].into_iter().flatten() {
+            if !corner.is_empty() {
+                corner.push(' ');
+            }
+            corner += &part;
+        }
+        let corner_width = if corner.is_empty() {
+            0
+        } else {
+            text_width(&corner) + 1
+        };
+
+        // A pending prompt takes the place of the message
+        let prompt = match &self.mode {
+            Mode::Confirm(confirm) => Some(self.config.confirm.render(confirm.prompt())),
+            _ => None,
         };
 
-        vec.swap(idx_val, new_idx);
-        *idx = new_idx;
+        if let Some(msg) = prompt.as_ref().or(self.status_msg.as_ref()) {
+            // Keep the message on the status row
+            let room = (cols as usize).saturating_sub(used + corner_width + 1);
+            let msg: String = msg.chars().take(room).collect();
+            frame.write_text(&format!(" {msg}"), false);
+        }
+
+        if !corner.is_empty() {
+            frame.goto(
+                cols.saturating_sub(text_width(&corner) as u16 + 1),
+                rows - 1,
+            );
+            frame.write_text(&corner, false);
+        }
     }
 
-    fn handle_help_mode(&mut self, action: Action) {
-        match action {
-            Action::SaveQuit | Action::NoSaveQuit => self.mode = Mode::Normal,
-            _ => {}
+    /// Register, count, operator and leader typed so far, cleared once the
+    /// binding completes or the leader times out
+    fn pending_keys(&self) -> String {
+        let mut keys = String::new();
+        if let Some(name) = self.active_register {
+            keys.push('"');
+            keys.push(name);
+        } else if self.awaiting_register {
+            keys.push('"');
+        }
+        if let Some((_, count)) = self.pending_delete {
+            if let Some(count) = count {
+                keys.push_str(&count.to_string());
+            }
+            keys.push('d');
+        }
+        if let Some(count) = self.pending_count {
+            keys.push_str(&count.to_string());
+        }
+        if self.leader_since.is_some() {
+            keys.push_str("<leader>");
+        }
+        keys
+    }
+
+    /// One letter per active toggle, shown next to the mode name: numbers,
+    /// wrapped items, a filter, a sorted list. `+` marks unsaved changes
+    fn status_flags(&self) -> String {
+        let flags = [
+            (self.show_number != Numbers::Off, 'N'),
+            (!self.truncate, 'W'),
+            (self.filter.is_some(), 'F'),
+            (
+                self.sort_modes[self.curr_tab as usize] != SortMode::Insertion,
+                'S',
+            ),
+            (self.dirty, '+'),
+        ];
+        flags
+            .iter()
+            .filter_map(|&(on, flag)| on.then_some(flag))
+            .collect()
+    }
+
+    fn write_help_screen(&self, frame: &mut Frame, term_size: (u16, u16)) {
+        for line in &self.static_text(term_size).help_lines {
+            frame.write_text(line, false);
+            frame.write_text("\r\n", false);
+        }
+    }
+
+    fn write_todos_dones(&self, frame: &mut Frame, term_size: (u16, u16)) {
+        let (cols, rows) = term_size;
+        // Nothing is drawn over the hints and status line, or past the bottom
+        // of the screen
+        let last_row = rows.saturating_sub(self.bottom_rows());
+
+        // Archived items are shown dimmed
+        let archived = matches!(self.mode, Mode::Archived);
+        frame.set_dim(archived);
+
+        let today = today();
+
+        let mut draw_items = |tab: Tab, is_active_tab: bool| {
+            let (items, line_begin, selected_idx) = self.column(tab);
+            let (col_offset, max_width) = self.column_geometry(tab, cols);
+            let query = match &self.mode {
+                Mode::Search(query) if is_active_tab => query.as_str(),
+                _ => "",
+            };
+            let mut current_line = 1;
+            for idx in self.scroll(tab)..items.len() {
+                if current_line >= last_row {
+                    break;
+                }
+                if self.is_hidden(&items[idx]) || self.is_folded(items, idx) {
+                    continue;
+                }
+                let selected = match self.visual_range() {
+                    Some(range) => range.contains(&idx),
+                    None => idx == selected_idx,
+                };
+                let should_highlight = is_active_tab && selected;
+                let heading = heading_text(&items[idx]);
+                // The other column keeps showing where its cursor is, more quietly
+                let inactive_selected = (!is_active_tab && idx == selected_idx)
+                    .then_some(self.config.theme.inactive_selected)
+                    .flatten();
+                let highlight = if should_highlight {
+                    Some(self.config.theme.selected)
+                } else {
+                    inactive_selected.or(heading.map(|_| Highlight::Bold))
+                };
+                // Graphemes matching the search get underlined once the item is drawn
+                let matched = (!should_highlight)
+                    .then(|| fuzzy_match(self.search_text(&items[idx]), query))
+                    .flatten();
+
+                // Dones show when they were completed instead, they can't be late
+                let (_, date) = self.shown_date(&items[idx]);
+                let overdue = tab != Tab::Dones && date.is_some_and(|date| date < today.as_str());
+                let theme = &self.config.theme;
+                let priority = split_priority(&items[idx]).0;
+                let priority_color = PRIORITIES
+                    .iter()
+                    .position(|&p| Some(p) == priority)
+                    .filter(|_| tab != Tab::Dones)
+                    .map(|i| theme.priority_colors[i]);
+                let foreground = if overdue {
+                    Some(theme.overdue)
+                } else if should_highlight && theme.selected_fg.is_some() {
+                    theme.selected_fg
+                } else {
+                    priority_color
+                };
+                frame.set_foreground(foreground);
+
+                let lines = self.wrap_item(items, idx, line_begin, max_width);
+                // Sits right before the text of the wrapped rows, when the
+                // indent has room for it
+                let marker = (self.config.theme.wrap_marker.as_deref())
+                    .filter(|_| lines.len() > 1)
+                    .map(|marker| (marker, self.full_line(items, idx, line_begin).1))
+                    .filter(|(marker, indent)| text_width(marker) < *indent);
+                let first_line = current_line;
+                for (i, line) in lines.iter().enumerate() {
+                    if current_line >= last_row {
+                        break;
+                    }
+                    frame.goto(col_offset, current_line);
+                    self.write_tagged(frame, line, highlight, foreground);
+                    // Fill the whole column so the highlight forms a solid block
+                    if should_highlight {
+                        let fill = max_width.saturating_sub(text_width(line));
+                        frame.write_highlighted(&" ".repeat(fill), highlight);
+                    }
+                    if let Some((marker, indent)) = marker
+                        && i > 0
+                    {
+                        let marker_col = indent - text_width(marker) - 1;
+                        frame.goto(col_offset + marker_col as u16, current_line);
+                        frame.set_dim(true);
+                        frame.write_highlighted(marker, highlight);
+                        frame.set_dim(archived);
+                    }
+                    current_line += 1;
+                }
+                // Without styling the highlight doesn't show, a `>` stands in for it
+                if self.config.plain && should_highlight && first_line < last_row {
+                    frame.goto(col_offset, first_line);
+                    frame.write_text(">", false);
+                }
+                if let Some(matched) = matched {
+                    let full_line = self.full_line(items, idx, line_begin);
+                    let top = (col_offset, first_line);
+                    self.write_match_marks(frame, &lines, full_line, &matched, top, last_row);
+                }
+
+                // The date, or else the age, sits at the right end of the
+                // item's first line
+                if let Some(label) = self.date_label(&items[idx])
+                    && first_line < last_row
+                {
+                    let label_col =
+                        col_offset + max_width.saturating_sub(text_width(&label)) as u16;
+                    frame.goto(label_col, first_line);
+                    frame.set_dim(archived || tab == Tab::Dones || date.is_none());
+                    frame.write_highlighted(&label, highlight);
+                    frame.set_dim(archived);
+                }
+                frame.set_foreground(None);
+            }
+        };
+
+        // The someday list is a separate full-width view, like either tab when
+        // the terminal is too narrow for two columns
+        if matches!(self.curr_tab, Tab::Someday) || self.is_single_column(cols) {
+            draw_items(self.curr_tab, true);
+        } else {
+            for tab in self.column_tabs() {
+                draw_items(tab, self.curr_tab == tab);
+            }
+        }
+        frame.set_dim(false);
+    }
+
+    /// Writes one row of an item, drawing its `#tags` in their own color
+    fn write_tagged(
+        &self,
+        frame: &mut Frame,
+        line: &str,
+        highlight: Option<Highlight>,
+        foreground: Option<Color>,
+    ) {
+        for word in line.split_inclusive(' ') {
+            let tag = word.trim_end_matches(' ');
+            if is_tag(tag) {
+                frame.set_foreground(Some(self.config.theme.tag));
+                frame.write_highlighted(tag, highlight);
+                frame.set_foreground(foreground);
+                frame.write_highlighted(&word[tag.len()..], highlight);
+            } else {
+                frame.write_highlighted(word, highlight);
+            }
+        }
+    }
+
+    /// Underlines the graphemes of `matched` over the `lines` an item was drawn
+    /// as from `top`, given its unwrapped line and continuation indent
+    fn write_match_marks(
+        &self,
+        frame: &mut Frame,
+        lines: &[String],
+        (full_line, offset): (String, usize),
+        matched: &FuzzyMatch,
+        (col, top): (u16, u16),
+        last_row: u16,
+    ) {
+        // The searched text ends the line, after any checkbox or number
+        let mut full = full_line.graphemes(true).enumerate();
+        let text_start = full.clone().count() - matched.len;
+
+        for (row, line) in (top..last_row).zip(lines) {
+            let pad = if row == top { 0 } else { offset };
+            let mut x = col + pad as u16;
+            for g in line.graphemes(true).skip(pad) {
+                // A cut off line ends in `…`, past the text it shows
+                let Some((n, _)) = full.next().filter(|&(_, full_g)| full_g == g) else {
+                    break;
+                };
+                if n >= text_start && matched.positions.contains(&(n - text_start)) {
+                    frame.goto(x, row);
+                    frame.write_highlighted(g, Some(Highlight::Underline));
+                }
+                x += grapheme_width(g) as u16;
+            }
+        }
+    }
+}
+
+/// Actions / Mode Handling
+impl App {
+    fn execute_action(&mut self, action: Action) -> anyhow::Result<()> {
+        let pending_count = self.pending_count.take();
+        if self.refuse_read_only(action) || self.refuse_filtered(action) {
+            return Ok(());
+        }
+        let count = pending_count.unwrap_or(1);
+        // Repeating a motion more often than there are items changes nothing
+        let steps = count.min(self.column(self.curr_tab).0.len());
+        if action.is_mutating() {
+            self.checkpoint();
+        }
+        match action {
+            Action::Enter => self.handle_enter_press(true, None),
+            Action::EnterStay => self.handle_enter_press(false, None),
+            Action::SwitchTab(tab) => self.switch_tab(tab),
+            Action::Insert(direction) => self.start_insert_mode(direction),
+            Action::Edit => self.start_edit_mode(false),
+            Action::ClearEdit => self.start_edit_mode(true),
+            Action::MoveCursor(direction) => {
+                for _ in 0..steps {
+                    self.handle_cursor_move(direction, self.config.wrap_cursor);
+                }
+            }
+            Action::HalfPage(direction) => self.move_page(direction, 2),
+            Action::FullPage(direction) => self.move_page(direction, 1),
+            Action::MoveItem(direction) => {
+                self.forget_sort();
+                for _ in 0..steps {
+                    self.handle_move_item(direction);
+                }
+            }
+            Action::MoveItemToEnd(direction) => {
+                self.forget_sort();
+                self.move_item_to_end(direction);
+            }
+            // With a count, `g` and `G` go to that item like `gg` and `G` in vim.
+            // The number matches the one shown by `show_number`
+            Action::GotoBegin | Action::GotoEnd if pending_count.is_some() => {
+                self.goto_list_pos(count.saturating_sub(self.number_base()))
+            }
+            Action::GotoBegin => self.goto_list_pos(0),
+            Action::GotoEnd => self.goto_list_pos(usize::MAX),
+            Action::Delete => {
+                let deleted = if self.filter.is_some() {
+                    self.delete_shown(count)
+                } else {
+                    self.handle_delete(count)
+                };
+                if !deleted.is_empty() {
+                    self.store_in_register(deleted);
+                }
+            }
+            Action::DeleteOperator => self.pending_delete = Some((Instant::now(), pending_count)),
+            Action::SaveQuit if self.empties_file() => {
+                self.mode = Mode::Confirm(Confirm::SaveEmpty)
+            }
+            Action::SaveQuit => anyhow::bail!(globals::BREAK),
+            // Unsaved changes are never dropped without asking
+            Action::NoSaveQuit if self.config.confirm_quit || self.dirty => {
+                self.mode = Mode::Confirm(Confirm::QuitNoSave)
+            }
+            Action::NoSaveQuit => anyhow::bail!(globals::NO_SAVE),
+            Action::ShowHelp => self.mode = Mode::Help,
+            Action::ShowFullText if self.get_current_buffer().is_some() => {
+                self.mode = Mode::FullText
+            }
+            Action::ShowFullText => {}
+            Action::ToggleTruncate => self.truncate = !self.truncate,
+            Action::ToggleSingleColumn => self.single_column = !self.single_column,
+            Action::ShowNumber => self.show_number = self.show_number.next(),
+            // Anything written over the screen is gone after a full repaint
+            Action::Redraw => self.last_frame = None,
+            Action::SelectRegister => self.awaiting_register = true,
+            Action::Yank => self.handle_yank(),
+            Action::Paste(direction) => self.handle_paste(direction),
+            Action::YankClipboard => {
+                self.active_register = Some(globals::CLIPBOARD_REGISTER);
+                self.handle_yank();
+            }
+            Action::PasteClipboard => {
+                self.active_register = Some(globals::CLIPBOARD_REGISTER);
+                self.handle_paste(KeyCode::Down);
+            }
+            Action::Archive => self.handle_archive(),
+            Action::ShowArchived => {
+                if matches!(self.curr_tab, Tab::Someday) {
+                    self.curr_tab = Tab::Todos;
+                }
+                self.archived_idx = 0;
+                self.mode = Mode::Archived;
+            }
+            Action::ToggleSomeday => {
+                self.curr_tab = match self.curr_tab {
+                    Tab::Someday => Tab::Todos,
+                    _ => Tab::Someday,
+                }
+            }
+            Action::ShuttleSomeday => self.handle_shuttle_someday(),
+            Action::MoveToOtherTab => self.handle_move_to_other_tab(),
+            Action::Sort => self.handle_sort(),
+            Action::Indent(subtask) => self.set_subtask(subtask),
+            Action::ToggleCollapse => self.toggle_collapse(),
+            Action::CyclePriority => self.cycle_priority(),
+            Action::SortPriority => self.handle_sort_priority(),
+            Action::ExternalEdit => self.open_in_editor()?,
+            Action::InsertHeading => self.start_heading_insert(),
+            Action::CommandLine => self.mode = Mode::Command(String::new()),
+            Action::Search => self.mode = Mode::Search(String::new()),
+            Action::SearchNext(backwards) => match self.last_search.clone() {
+                Some(query) => self.jump_to_match(&query, backwards, true),
+                None => self.status_msg = Some("No previous search".into()),
+            },
+            Action::JumpDue => {
+                let today = today();
+                self.jump_to_next("No item due by today", |entry| {
+                    split_due(entry).1.is_some_and(|due| due <= today.as_str())
+                });
+            }
+            Action::JumpPriority => self.jump_to_next("No item of priority (A)", |entry| {
+                split_priority(entry).0 == Some(PRIORITIES[0])
+            }),
+            Action::Visual => {
+                let (items, _, idx) = self.column(self.curr_tab);
+                if !items.is_empty() {
+                    self.mode = Mode::Visual(idx);
+                }
+            }
+            Action::ClearList if self.column(self.curr_tab).0.is_empty() => {
+                self.status_msg = Some("Nothing to delete".into())
+            }
+            Action::ClearList => self.mode = Mode::Confirm(Confirm::ClearList),
+            Action::OpenFile => {
+                self.mode = Mode::Command(format!("e {}", self.file_path.display()))
+            }
+            Action::SetDue => {
+                let (_, due) = self
+                    .get_current_buffer()
+                    .map_or(("", None), |item| split_due(item));
+                self.mode = Mode::Command(format!("due {}", due.unwrap_or_default()));
+            }
+            // Starts from the filter in use, or else the item's first tag
+            Action::FilterTag => {
+                let current = match &self.filter {
+                    Some(Filter::Tag(tag)) => Some(tag.clone()),
+                    _ => None,
+                };
+                let tag = current.or_else(|| {
+                    let item = self.get_current_buffer()?;
+                    first_tag(item).map(str::to_lowercase)
+                });
+                self.mode = Mode::Command(format!("tag {}", tag.unwrap_or_default()));
+            }
+            // The todos due by today, or every item again
+            Action::FilterDue => {
+                self.filter = match self.filter {
+                    Some(Filter::Due(_)) => None,
+                    _ => Some(Filter::Due(today())),
+                };
+            }
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
+        }
+
+        Ok(())
+    }
+
+    fn switch_tab(&mut self, tab: TabAction) {
+        self.curr_tab = match tab {
+            TabAction::Toggle => self.curr_tab.toggle(),
+            // Left and right follow the columns, which `swap_columns` flips
+            TabAction::Left => self.column_tabs()[0],
+            TabAction::Right => self.column_tabs()[1],
+            // Out of range positions are ignored
+            TabAction::Index(n) => Tab::nth(n).unwrap_or(self.curr_tab),
+        };
+    }
+
+    /// Saves, hands the file over to `$VISUAL`/`$EDITOR` and reloads it afterwards
+    fn open_in_editor(&mut self) -> anyhow::Result<()> {
+        // The editor couldn't write its changes back either
+        if let Err(e) = self.save() {
+            self.status_msg = Some(format!("Failed to save: {e}"));
+            return Ok(());
+        }
+        reset_scr(self.config.no_alt_screen)?;
+        io::stdout().flush()?;
+
+        let editor = env::var("VISUAL")
+            .or_else(|_| env::var("EDITOR"))
+            .unwrap_or_else(|_| "vi".into());
+        // Allow editors given with arguments, like `code --wait`
+        let mut words = editor.split_whitespace();
+        let program = words.next().unwrap_or("vi");
+        let status = process::Command::new(program)
+            .args(words)
+            .arg(&self.file_path)
+            .status();
+
+        init_scr(self.config.mouse, self.config.no_alt_screen)?;
+        // The editor left the screen in an unknown state
+        self.last_frame = None;
+
+        match status {
+            Ok(status) if !status.success() => {
+                self.status_msg = Some(format!("{program} exited with {status}"));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                self.status_msg = Some(format!("Unable to launch {program}: {e}"));
+                return Ok(());
+            }
+        }
+
+        self.reload()
+    }
+
+    /// Picks up changes other programs made to the file, returns whether it
+    /// changed. Unsaved edits are only dropped once the user agrees to
+    fn check_file_changed(&mut self) -> anyhow::Result<bool> {
+        let mtime = modified(&self.file_path);
+        if mtime.is_none() || mtime == self.file_mtime {
+            return Ok(false);
+        }
+
+        if self.dirty {
+            self.mode = Mode::Confirm(Confirm::ExternalChange);
+        } else {
+            // A file that can't be loaded is reported once, not on every check
+            self.file_mtime = mtime;
+            self.reload()?;
+            self.status_msg = Some("Reloaded, the file changed on disk".into());
+        }
+        Ok(true)
+    }
+
+    /// Sorts the current list ignoring case, descending if it's already in
+    /// ascending order. Headings keep their place and the items under each are
+    /// sorted on their own. Subtasks go with their item, in the same order.
+    /// The cursor follows the item it was on
+    fn handle_sort(&mut self) {
+        let (list, _) = self.current_list_mut();
+        let sorted = Self::sorted_order(list, SortMode::Ascending).is_sorted();
+        let (mode, msg) = if sorted {
+            (SortMode::Descending, "Sorted Z-A")
+        } else {
+            (SortMode::Ascending, "Sorted A-Z")
+        };
+        self.sort_current(mode);
+        self.status_msg = Some(msg.into());
+    }
+
+    /// Stable sort of the current list by priority, items without one going
+    /// last
+    fn handle_sort_priority(&mut self) {
+        self.sort_current(SortMode::Priority);
+        self.status_msg = Some("Sorted by priority".into());
+    }
+
+    /// Sorts the current list by `mode` and remembers it did, returning
+    /// whether any item moved
+    fn sort_current(&mut self, mode: SortMode) -> bool {
+        self.sort_modes[self.curr_tab as usize] = mode;
+        let (list, idx) = self.current_list_mut();
+        let order = Self::sorted_order(list, mode);
+        if order.is_sorted() {
+            return false;
+        }
+        *idx = order.iter().position(|&i| i == *idx).unwrap_or(*idx);
+        *list = order.iter().map(|&i| mem::take(&mut list[i])).collect();
+        true
+    }
+
+    /// Indexes of `list` in the order `mode` sorts it. Headings keep their
+    /// place, only the items between them are sorted, each with its subtasks
+    fn sorted_order(list: &[String], mode: SortMode) -> Vec<usize> {
+        let is_heading = |&i: &usize| heading_text(&list[i]).is_some();
+        let parent = |i: usize| parent_of(list, i).unwrap_or(i);
+        let text = |i: usize| entry_text(&list[parent(i)]).to_lowercase();
+        let priority = |i: usize| split_priority(&list[parent(i)]).0.unwrap_or(char::MAX);
+        let done = |i: usize| split_done(&list[parent(i)]).1;
+
+        let mut order: Vec<usize> = (0..list.len()).collect();
+        for section in order.split_mut(is_heading) {
+            // Stable, so each item stays ahead of its subtasks
+            match mode {
+                SortMode::Insertion => {}
+                SortMode::Ascending => section.sort_by_cached_key(|&i| (text(i), parent(i))),
+                SortMode::Descending => {
+                    section.sort_by_cached_key(|&i| (Reverse(text(i)), parent(i)))
+                }
+                SortMode::Priority => section.sort_by_key(|&i| (priority(i), parent(i))),
+                SortMode::Completion => section.sort_by_key(|&i| (Reverse(done(i)), parent(i))),
+            }
+        }
+        order
+    }
+
+    /// Items of the current list were put in an order of their own
+    fn forget_sort(&mut self) {
+        self.sort_modes[self.curr_tab as usize] = SortMode::Insertion;
+    }
+
+    /// State key of the sort mode of `tab` in `file`
+    fn sort_key(tab: Tab, file: &Path) -> String {
+        format!("sort_{tab} {}", file.display())
+    }
+
+    /// Sorts the lists the way they were when `file` was last closed. Modes
+    /// that aren't known keep the order they were saved in. The dones are
+    /// sorted by completion date instead with `dones_newest_first`
+    fn restore_sort_modes(&mut self, ui_state: &UiState, file: &Path) {
+        let tab = self.curr_tab;
+        for list in [Tab::Todos, Tab::Dones, Tab::Someday] {
+            let mode = if list == Tab::Dones && self.config.dones_newest_first {
+                SortMode::Completion
+            } else {
+                ui_state
+                    .get(&Self::sort_key(list, file))
+                    .unwrap_or_default()
+            };
+            self.curr_tab = list;
+            self.dirty |= self.sort_current(mode);
+        }
+        self.curr_tab = tab;
+    }
+
+    fn store_sort_modes(&self, ui_state: &mut UiState, file: &Path) {
+        for list in [Tab::Todos, Tab::Dones, Tab::Someday] {
+            let key = Self::sort_key(list, file);
+            match self.sort_modes[list as usize] {
+                // Completion order comes from the config, not the last session
+                SortMode::Insertion | SortMode::Completion => ui_state.remove(&key),
+                mode => ui_state.set(&key, mode),
+            }
+        }
+    }
+
+    /// Steps the priority of the item under the cursor down, from none to
+    /// the highest and past the lowest back to none
+    fn cycle_priority(&mut self) {
+        if self.on_heading() {
+            return;
+        }
+        let (list, idx) = self.current_list_mut();
+        let Some(item) = list.get_mut(*idx) else {
+            return;
+        };
+
+        let next = match split_priority(item).0 {
+            None => Some(PRIORITIES[0]),
+            Some(p) => PRIORITIES.iter().skip_while(|&&q| q != p).nth(1).copied(),
+        };
+        *item = with_priority(item, next);
+    }
+
+    /// Makes the item under the cursor a subtask of the item above it, or an
+    /// item of its own again. Headings, and items right below one, stay as
+    /// they are
+    fn set_subtask(&mut self, subtask: bool) {
+        if self.on_heading() {
+            return;
+        }
+        let (list, idx) = self.current_list_mut();
+        let idx = *idx;
+        let Some(entry) = list.get(idx) else {
+            return;
+        };
+
+        let text = subtask_text(entry);
+        let under_item = idx > 0 && heading_text(&list[idx - 1]).is_none();
+        if subtask && text.is_none() && under_item {
+            list[idx].insert(0, SUBTASK_MARK);
+            // Shown, rather than disappearing under a collapsed item
+            let parent = parent_of(list, idx).map(|parent| list[parent].clone());
+            self.collapsed
+                .retain(|entry| Some(entry) != parent.as_ref());
+        } else if let Some(text) = text.filter(|_| !subtask) {
+            list[idx] = text.to_string();
+        }
+    }
+
+    /// Hides the subtasks of the item under the cursor, or of the item the
+    /// subtask under it belongs to, or shows them again
+    fn toggle_collapse(&mut self) {
+        let (items, _, idx) = self.column(self.curr_tab);
+        if items.is_empty() {
+            return;
+        }
+        let parent = parent_of(items, idx).unwrap_or(idx);
+        if subtask_count(items, parent) == 0 {
+            return;
+        }
+
+        let entry = items[parent].clone();
+        if !self.collapsed.remove(&entry) {
+            self.collapsed.insert(entry);
+            // The cursor can't stay on a subtask that's now hidden
+            *self.current_list_mut().1 = parent;
+        }
+    }
+
+    /// Moves the item under the cursor and its subtasks to the other tab at
+    /// the same position, without completing it. Headings stay where they are.
+    /// The cursor stays put, landing on the next item
+    fn handle_move_to_other_tab(&mut self) {
+        if self.on_heading() {
+            return;
+        }
+
+        let (from, idx, to) = match self.curr_tab {
+            Tab::Todos => (&mut self.todos, self.todos_idx, &mut self.dones),
+            Tab::Dones => (&mut self.dones, self.dones_idx, &mut self.todos),
+            Tab::Someday => (&mut self.someday, self.someday_idx, &mut self.todos),
+        };
+
+        if from.is_empty() {
+            return;
+        }
+
+        let to_tab = self.curr_tab.toggle();
+        let item = from.remove(idx);
+        let subtasks = if subtask_text(&item).is_none() {
+            Self::detach_subtasks(from, idx, true)
+        } else {
+            Vec::new()
+        };
+        // Past the subtasks there, which stay with the item they're under
+        let mut at = idx.min(to.len());
+        while to
+            .get(at)
+            .is_some_and(|entry| subtask_text(entry).is_some())
+        {
+            at += 1;
+        }
+        let subtasks = subtasks
+            .into_iter()
+            .map(|entry| format!("{}{}", SUBTASK_MARK, Self::restamp(entry, to_tab)));
+        to.insert(at, Self::restamp(item, to_tab));
+        to.splice(at + 1..at + 1, subtasks);
+    }
+
+    /// Sends the item under the cursor and its subtasks to the someday list,
+    /// or back to the todos when already there
+    fn handle_shuttle_someday(&mut self) {
+        if self.on_heading() {
+            return;
+        }
+
+        let (from, idx, to) = match self.curr_tab {
+            Tab::Todos => (&mut self.todos, self.todos_idx, &mut self.someday),
+            Tab::Dones => (&mut self.dones, self.dones_idx, &mut self.someday),
+            Tab::Someday => (&mut self.someday, self.someday_idx, &mut self.todos),
+        };
+
+        if from.is_empty() {
+            return;
+        }
+
+        let to_tab = match self.curr_tab {
+            Tab::Someday => Tab::Todos,
+            Tab::Todos | Tab::Dones => Tab::Someday,
+        };
+        let block = Self::take_block(from, idx).into_iter().enumerate();
+        to.extend(block.map(|(i, entry)| match i {
+            0 => Self::restamp(entry, to_tab),
+            _ => format!("{}{}", SUBTASK_MARK, Self::restamp(entry, to_tab)),
+        }));
+    }
+
+    /// Hides the item under the cursor and its subtasks, keeping them in the
+    /// file as archived
+    fn handle_archive(&mut self) {
+        if self.on_heading() {
+            return;
+        }
+
+        let (list, idx, archive) = match self.curr_tab {
+            Tab::Todos => (&mut self.todos, self.todos_idx, &mut self.archived_todos),
+            Tab::Dones => (&mut self.dones, self.dones_idx, &mut self.archived_dones),
+            Tab::Someday => return,
+        };
+
+        if list.is_empty() {
+            return;
+        }
+
+        archive.extend(Self::take_block(list, idx));
+    }
+
+    /// Moving the cursor grows or shrinks the selection, the other actions
+    /// apply to every selected item at once and end it
+    fn handle_visual_mode(&mut self, action: Action) {
+        let Some(range) = self.visual_range() else {
+            return;
+        };
+        if self.refuse_read_only(action) {
+            return;
+        }
+        // `d` deletes the selection right away, rather than waiting for a motion
+        if action.is_mutating() || matches!(action, Action::DeleteOperator) {
+            self.checkpoint();
+        }
+
+        let ends_selection = match action {
+            Action::MoveCursor(direction) => {
+                self.handle_cursor_move(direction, false);
+                false
+            }
+            Action::HalfPage(direction) | Action::FullPage(direction) => {
+                let fraction = if matches!(action, Action::HalfPage(_)) {
+                    2
+                } else {
+                    1
+                };
+                self.move_page(direction, fraction);
+                false
+            }
+            Action::GotoBegin => {
+                self.goto_list_pos(0);
+                false
+            }
+            Action::GotoEnd => {
+                self.goto_list_pos(usize::MAX);
+                false
+            }
+            // The selection moves along with the block
+            Action::MoveItem(direction) => {
+                self.forget_sort();
+                let (list, idx) = self.current_list_mut();
+                let shift = match direction {
+                    KeyCode::Up if range.start > 0 => {
+                        list[range.start - 1..range.end].rotate_left(1);
+                        -1
+                    }
+                    KeyCode::Down if range.end < list.len() => {
+                        list[range.start..=range.end].rotate_right(1);
+                        1
+                    }
+                    _ => 0,
+                };
+                *idx = idx.saturating_add_signed(shift);
+                if let Mode::Visual(anchor) = &mut self.mode {
+                    *anchor = anchor.saturating_add_signed(shift);
+                }
+                false
+            }
+            Action::Delete | Action::DeleteOperator => {
+                let (list, idx) = self.current_list_mut();
+                let deleted = list.drain(range.clone()).collect();
+                *idx = range.start.min(list.len().saturating_sub(1));
+                self.store_in_register(deleted);
+                true
+            }
+            Action::Yank => {
+                let (list, idx) = self.current_list_mut();
+                let yanked = list[range.clone()].to_vec();
+                *idx = range.start;
+                self.store_in_register(yanked);
+                true
+            }
+            // Items are taken one by one from the start of the range, each
+            // moving the rest up. Headings stay behind, and subtasks carried
+            // along with their item count as taken, so nothing past the
+            // selection moves
+            Action::Enter | Action::EnterStay => {
+                let mut next = range.start;
+                let mut left = range.len();
+                while left > 0 {
+                    *self.current_list_mut().1 = next;
+                    if self.on_heading() {
+                        next += 1;
+                        left -= 1;
+                        continue;
+                    }
+                    let (tab, before) = (self.curr_tab, self.column(self.curr_tab).0.len());
+                    let follow = left == 1 && matches!(action, Action::Enter);
+                    self.handle_enter_press(follow, Some(range.end - left));
+                    let taken = before - self.column(tab).0.len();
+                    if taken == 0 {
+                        break;
+                    }
+                    left = left.saturating_sub(taken);
+                }
+                true
+            }
+            Action::Visual => true,
+            _ => false,
+        };
+        if ends_selection {
+            self.mode = Mode::Normal;
+        }
+    }
+
+    /// Only navigation and restoring are possible while viewing archived items
+    fn handle_archived_mode(&mut self, action: Action) {
+        if self.refuse_read_only(action) {
+            return;
+        }
+        match action {
+            Action::MoveCursor(KeyCode::Down) => self.archived_idx += 1,
+            Action::MoveCursor(KeyCode::Up) => {
+                self.archived_idx = self.archived_idx.saturating_sub(1)
+            }
+            Action::GotoBegin => self.archived_idx = 0,
+            Action::GotoEnd => self.archived_idx = usize::MAX,
+            // Someday items are never archived
+            Action::SwitchTab(TabAction::Index(n)) if matches!(Tab::nth(n), Some(Tab::Someday)) => {
+            }
+            Action::SwitchTab(tab) => {
+                self.switch_tab(tab);
+                self.archived_idx = 0;
+            }
+            Action::Archive => {
+                self.checkpoint();
+                let (archive, list) = match self.curr_tab {
+                    Tab::Todos => (&mut self.archived_todos, &mut self.todos),
+                    Tab::Dones => (&mut self.archived_dones, &mut self.dones),
+                    Tab::Someday => return,
+                };
+                if !archive.is_empty() {
+                    list.extend(Self::take_block(archive, self.archived_idx));
+                }
+            }
+            Action::ShowArchived | Action::SaveQuit | Action::NoSaveQuit => {
+                self.mode = Mode::Normal
+            }
+            _ => {}
+        }
+    }
+
+    /// Moves the item under the cursor to the other list. With `follow` the
+    /// cursor jumps to the moved item, otherwise it stays on the current list.
+    ///
+    /// It goes to the end of that list, or with `complete_in_place` to `slot`,
+    /// by default the index it had in its own. With `dones_newest_first` a
+    /// completed todo goes above the dones completed before today
+    fn handle_enter_press(&mut self, follow: bool, slot: Option<usize>) {
+        // Headings aren't tasks, there is nothing to complete
+        if self.on_heading() {
+            return;
+        }
+
+        if self.config.complete_removes && matches!(self.curr_tab, Tab::Todos) {
+            self.remove_completed();
+            return;
+        }
+
+        let verb = match self.curr_tab {
+            Tab::Todos => "Completed",
+            Tab::Dones => "Reopened",
+            Tab::Someday => "Moved to todos",
+        };
+        let (from, from_idx, to, to_idx, to_tab) = match self.curr_tab {
+            Tab::Todos => (
+                &mut self.todos,
+                self.todos_idx,
+                &mut self.dones,
+                &mut self.dones_idx,
+                Tab::Dones,
+            ),
+            Tab::Dones => (
+                &mut self.dones,
+                self.dones_idx,
+                &mut self.todos,
+                &mut self.todos_idx,
+                Tab::Todos,
+            ),
+            // Someday items become actionable again
+            Tab::Someday => (
+                &mut self.someday,
+                self.someday_idx,
+                &mut self.todos,
+                &mut self.todos_idx,
+                Tab::Todos,
+            ),
+        };
+
+        if from.is_empty() {
+            return;
+        }
+
+        let value = from.remove(from_idx);
+        let subtasks = if subtask_text(&value).is_none() {
+            Self::detach_subtasks(from, from_idx, self.config.complete_subtasks)
+        } else {
+            Vec::new()
+        };
+        let completed = matches!(self.curr_tab, Tab::Todos).then(|| value.clone());
+        let moved = value.clone();
+        let end = match self.curr_tab {
+            Tab::Todos => self.config.completed_position,
+            Tab::Dones => self.config.reopened_position,
+            Tab::Someday => ListEnd::Bottom,
+        };
+        let at = if to_tab == Tab::Dones && self.config.dones_newest_first {
+            let date = today();
+            to.iter()
+                .position(|entry| {
+                    heading_text(entry).is_none()
+                        && subtask_text(entry).is_none()
+                        && split_done(entry).1.is_none_or(|done| done <= date.as_str())
+                })
+                .unwrap_or(to.len())
+        } else if self.config.complete_in_place {
+            slot.unwrap_or(from_idx).min(to.len())
+        } else if end == ListEnd::Top {
+            0
+        } else {
+            to.len()
+        };
+        to.insert(at, Self::restamp(value, to_tab));
+        let subtasks = subtasks
+            .into_iter()
+            .map(|entry| format!("{}{}", SUBTASK_MARK, Self::restamp(entry, to_tab)));
+        to.splice(at + 1..at + 1, subtasks);
+
+        if follow {
+            *to_idx = at;
+            self.curr_tab = to_tab;
+        }
+        // The item may land out of sight at the end of the other list
+        self.report_move(verb, &moved);
+
+        if let Some(item) = completed {
+            self.run_hook("on_complete", self.config.on_complete.as_deref(), &item);
+        }
+    }
+
+    /// Completes the todo under the cursor by deleting it, logging it if enabled
+    fn remove_completed(&mut self) {
+        if self.todos.is_empty() {
+            return;
+        }
+
+        let value = self.todos.remove(self.todos_idx);
+        if subtask_text(&value).is_none() {
+            let take = self.config.complete_subtasks;
+            Self::detach_subtasks(&mut self.todos, self.todos_idx, take);
+        }
+        self.report_move("Completed", &value);
+        self.run_hook("on_complete", self.config.on_complete.as_deref(), &value);
+        if !self.config.completion_log {
+            return;
+        }
+
+        let mut log_path = self.file_path.clone().into_os_string();
+        log_path.push(".log");
+        let line = self.config.prefixes.line(&Item {
+            kind: Kind::Done,
+            text: with_done(entry_text(&value), Some(&today())),
+        });
+        if let Err(e) = append_line(log_path, &line) {
+            self.status_msg = Some(format!("Failed to log completion: {e}"));
+        }
+    }
+
+    /// Clicking an item's checkbox toggles it, keeping the cursor on its list.
+    /// Pressing anywhere else on an item selects it and starts a drag, which
+    /// moves the item along with the pointer until the button is released
+    fn handle_mouse_event(&mut self, event: MouseEvent, term_size: (u16, u16)) {
+        if !matches!(self.mode, Mode::Normal) {
+            return;
+        }
+
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => self.handle_mouse_press(event, term_size),
+            MouseEventKind::Drag(MouseButton::Left) => self.handle_mouse_drag(event, term_size),
+            MouseEventKind::Up(MouseButton::Left) => self.dragging = false,
+            MouseEventKind::ScrollUp => self.handle_cursor_move(KeyCode::Up, false),
+            MouseEventKind::ScrollDown => self.handle_cursor_move(KeyCode::Down, false),
+            _ => {}
+        }
+    }
+
+    fn handle_mouse_press(&mut self, event: MouseEvent, term_size: (u16, u16)) {
+        let col_mid = term_size.0 / 2;
+        let tab = match self.curr_tab {
+            Tab::Someday => Tab::Someday,
+            // Below the header, a single column only shows the active tab
+            tab if event.row > 0 && self.is_single_column(term_size.0) => tab,
+            _ if event.column < col_mid => self.column_tabs()[0],
+            _ => self.column_tabs()[1],
+        };
+        let (col_offset, _) = self.column_geometry(tab, term_size.0);
+        // Left of the column the click landed in, nothing is there to click
+        let Some(column) = event.column.checked_sub(col_offset) else {
+            return;
+        };
+
+        // Clicking the TODO / DONE header switches to that tab
+        if event.row == 0 {
+            self.curr_tab = tab;
+            return;
+        }
+
+        let Some((idx, first_row)) = self.item_at_row(tab, event.row, term_size) else {
+            return;
+        };
+
+        self.curr_tab = tab;
+        *self.current_list_mut().1 = idx;
+
+        // Clicks still move the cursor, but never toggle or drag an item
+        if self.config.read_only {
+            return;
+        }
+        let checkbox_width = text_width(self.column(tab).1);
+        if first_row && (column as usize) < checkbox_width {
+            self.checkpoint();
+            self.handle_enter_press(false, None);
+        } else {
+            self.dragging = true;
+            self.drag_moved = false;
+        }
+    }
+
+    fn handle_mouse_drag(&mut self, event: MouseEvent, term_size: (u16, u16)) {
+        if !self.dragging {
+            return;
+        }
+
+        // Rows past the end of the list drop the item at the bottom
+        let target = match self.item_at_row(self.curr_tab, event.row, term_size) {
+            Some((idx, _)) => idx,
+            None if event.row > 0 => usize::MAX,
+            None => 0,
+        };
+
+        let (list, idx) = self.current_list_mut();
+        if list.is_empty() {
+            return;
+        }
+
+        let target = target.min(list.len() - 1);
+        if target == *idx {
+            return;
+        }
+
+        // The whole drag is undone at once
+        if !self.drag_moved {
+            self.drag_moved = true;
+            self.checkpoint();
+            self.forget_sort();
+        }
+
+        let (list, idx) = self.current_list_mut();
+        let item = list.remove(*idx);
+        list.insert(target, item);
+        *idx = target;
+    }
+
+    /// Moves the cursor to the next item it stops on, going around to the
+    /// other end of the list with `wrap` when there's none left that way
+    fn handle_cursor_move(&mut self, direction: KeyCode, wrap: bool) {
+        let skip_headings = self.config.skip_headings;
+        let (list, _, idx) = self.column(self.curr_tab);
+        let stops_on = |i: &usize| {
+            let skipped = skip_headings && heading_text(&list[*i]).is_some();
+            !skipped && !self.is_hidden(&list[*i]) && !self.is_folded(list, *i)
+        };
+        let next = match direction {
+            KeyCode::Down => (idx + 1..list.len()).find(stops_on),
+            KeyCode::Up => (0..idx.min(list.len())).rev().find(stops_on),
+            _ => unreachable!("This spot should't be reachable"),
+        };
+        let next = next.or_else(|| match direction {
+            _ if !wrap => None,
+            KeyCode::Down => (0..idx.min(list.len())).find(stops_on),
+            _ => (idx + 1..list.len()).rev().find(stops_on),
+        });
+
+        // Only headings or filtered out items left in that direction
+        if let Some(next) = next {
+            *self.current_list_mut().1 = next;
+        }
+    }
+
+    /// Moves the cursor by `1 / fraction` of the rows items are drawn on
+    fn move_page(&mut self, direction: KeyCode, fraction: usize) {
+        // The header takes a row, like the status line and hints under the lists
+        let view_rows = self.term_size.1.saturating_sub(1 + self.bottom_rows()) as usize;
+        let steps = (view_rows / fraction).max(1);
+        for _ in 0..steps.min(self.column(self.curr_tab).0.len()) {
+            self.handle_cursor_move(direction, false);
+        }
+    }
+
+    /// Removes up to `count` items starting at the cursor, returning them
+    fn handle_delete(&mut self, count: usize) -> Vec<String> {
+        let (target_vec, idx) = self.current_list_mut();
+
+        if target_vec.is_empty() {
+            return Vec::new();
+        }
+
+        let end = idx.saturating_add(count).min(target_vec.len());
+        let deleted = target_vec.drain(*idx..end).collect();
+        // Like vim, the cursor stays put on what was below, only moving up
+        // when the deleted items were the last ones
+        *idx = (*idx).min(target_vec.len().saturating_sub(1));
+        deleted
+    }
+
+    /// Deletes the items `d` and the motion after it span: `dd` the item under
+    /// the cursor, `dj` / `dk` it and `count` more below / above, `dG` / `dg`
+    /// everything up to the end / start of the list. Any other key, like Esc,
+    /// drops the operator
+    fn apply_delete_operator(
+        &mut self,
+        count: usize,
+        motion: Result<Action, ()>,
+    ) -> anyhow::Result<()> {
+        let (items, _, before) = self.column(self.curr_tab);
+        if items.is_empty() {
+            return Ok(());
+        }
+        let total = match motion {
+            Ok(Action::DeleteOperator) => count,
+            Ok(Action::MoveCursor(KeyCode::Down)) => count.saturating_add(1),
+            Ok(Action::GotoEnd) => usize::MAX,
+            // Upwards the deletion starts where the motion lands
+            Ok(Action::MoveCursor(KeyCode::Up) | Action::GotoBegin) => {
+                let steps = match motion {
+                    Ok(Action::GotoBegin) => usize::MAX,
+                    _ => count,
+                };
+                for _ in 0..steps.min(items.len()) {
+                    self.handle_cursor_move(KeyCode::Up, false);
+                }
+                let (items, _, after) = self.column(self.curr_tab);
+                let spanned = &items[after..=before];
+                spanned.iter().filter(|e| !self.is_hidden(e)).count()
+            }
+            _ => return Ok(()),
+        };
+
+        self.pending_count = Some(total);
+        self.execute_action(Action::Delete)
+    }
+
+    /// Adds a typed digit to the count for the next action, returning whether
+    /// the key was one. A leading 0 isn't a count
+    fn push_count_digit(&mut self, code: KeyCode) -> bool {
+        let KeyCode::Char(c @ '0'..='9') = code else {
+            return false;
+        };
+        if c == '0' && self.pending_count.is_none() {
+            return false;
+        }
+        let digit = c as usize - '0' as usize;
+        let count = self.pending_count.unwrap_or(0);
+        self.pending_count = Some(count.saturating_mul(10).saturating_add(digit));
+        true
+    }
+
+    /// Like `handle_delete`, but skips the items the tag filter hides
+    fn delete_shown(&mut self, count: usize) -> Vec<String> {
+        let (items, _, idx) = self.column(self.curr_tab);
+        let picked: Vec<_> = (idx..items.len())
+            .filter(|&i| !self.is_hidden(&items[i]))
+            .take(count)
+            .collect();
+
+        let (list, _) = self.current_list_mut();
+        let mut deleted: Vec<_> = picked.iter().rev().map(|&i| list.remove(i)).collect();
+        deleted.reverse();
+        deleted
+    }
+
+    /// Moves the item under the cursor past the next item shown, so items
+    /// the tag filter hides keep their place
+    fn handle_move_item(&mut self, direction: KeyCode) {
+        let (items, _, idx) = self.column(self.curr_tab);
+        let shown = |i: &usize| !self.is_hidden(&items[*i]);
+        let target = match direction {
+            KeyCode::Down => (idx + 1..items.len()).find(shown),
+            KeyCode::Up => (0..idx.min(items.len())).rev().find(shown),
+            _ => unreachable!(),
+        };
+
+        if let Some(target) = target {
+            let (list, idx) = self.current_list_mut();
+            let item = list.remove(*idx);
+            list.insert(target, item);
+            *idx = target;
+        }
+    }
+
+    /// Moves the item under the cursor to the top or bottom of its list, the
+    /// cursor following it
+    fn move_item_to_end(&mut self, direction: KeyCode) {
+        let (list, idx) = self.current_list_mut();
+        if *idx >= list.len() {
+            return;
+        }
+
+        let item = list.remove(*idx);
+        let target = match direction {
+            KeyCode::Up => 0,
+            KeyCode::Down => list.len(),
+            _ => unreachable!(),
+        };
+        list.insert(target, item);
+        *idx = target;
+    }
+
+    /// Every confirmation goes through here, keys other than the configured
+    /// accept and reject ones are ignored
+    fn handle_confirm_mode(&mut self, event: KeyEvent) -> anyhow::Result<()> {
+        let accepted = if event.code == self.config.confirm.accept {
+            true
+        } else if event.code == self.config.confirm.reject {
+            false
+        } else {
+            return Ok(());
+        };
+
+        let Mode::Confirm(confirm) = mem::replace(&mut self.mode, Mode::Normal) else {
+            unreachable!("Only called while confirming");
+        };
+        match confirm {
+            Confirm::QuitNoSave if accepted => anyhow::bail!(globals::NO_SAVE),
+            Confirm::QuitNoSave => {}
+            Confirm::ExternalChange if accepted => self.reload()?,
+            // Keeping the edits means the next save overwrites the other changes
+            Confirm::ExternalChange => self.file_mtime = modified(&self.file_path),
+            Confirm::ClearList if accepted => self.clear_list(),
+            Confirm::ClearList => {}
+            Confirm::SaveEmpty if accepted => anyhow::bail!(globals::BREAK),
+            Confirm::SaveEmpty => {}
+        }
+        Ok(())
+    }
+
+    /// Empties the current list, sparing the items the tag filter hides
+    fn clear_list(&mut self) {
+        self.checkpoint();
+        let mut list = mem::take(self.current_list_mut().0);
+        list.retain(|entry| self.is_hidden(entry));
+        let (items, idx) = self.current_list_mut();
+        *items = list;
+        *idx = 0;
+        *self.scroll_mut(self.curr_tab) = 0;
+    }
+
+    fn undo(&mut self) {
+        let Some(snapshot) = self.undo_stack.pop_back() else {
+            self.status_msg = Some("Nothing to undo".into());
+            return;
+        };
+        let current = self.snapshot();
+        self.redo_stack.push(current);
+        self.restore(snapshot);
+    }
+
+    fn redo(&mut self) {
+        let Some(snapshot) = self.redo_stack.pop() else {
+            self.status_msg = Some("Nothing to redo".into());
+            return;
+        };
+        let current = self.snapshot();
+        self.undo_stack.push_back(current);
+        self.restore(snapshot);
+    }
+
+    fn handle_command_mode(&mut self, action: InsertAction) -> anyhow::Result<()> {
+        let Mode::Command(buf) = &mut self.mode else {
+            unreachable!("Only called in command mode");
+        };
+
+        match action {
+            InsertAction::Char(c) => buf.push(c),
+            // Erasing past the `:` leaves the command line, like in vim
+            InsertAction::DeleteChar if buf.is_empty() => self.mode = Mode::Normal,
+            InsertAction::DeleteChar => pop_grapheme(buf),
+            InsertAction::DeleteWord => {
+                let start = word_start(buf, buf.graphemes(true).count());
+                buf.truncate(grapheme_to_byte(buf, start));
+            }
+            InsertAction::Cancel | InsertAction::Quit => self.mode = Mode::Normal,
+            InsertAction::Enter => {
+                let Mode::Command(cmd) = mem::replace(&mut self.mode, Mode::Normal) else {
+                    unreachable!();
+                };
+                self.run_command(cmd.trim())?;
+            }
+            InsertAction::Left
+            | InsertAction::Right
+            | InsertAction::WordLeft
+            | InsertAction::WordRight
+            | InsertAction::Home
+            | InsertAction::End
+            | InsertAction::PasteRegister => {}
+        }
+        Ok(())
+    }
+
+    /// Text pasted into the terminal, which bracketed paste delivers in one
+    /// piece. While typing an item, each further line becomes an item of its
+    /// own below it, unless `paste_join` joins them by spaces.
+    /// The command line and search always join them
+    fn handle_paste_event(&mut self, text: &str) -> anyhow::Result<()> {
+        self.status_msg = None;
+        let mut lines = text
+            .split(['\r', '\n'])
+            .map(str::trim)
+            .filter(|line| !line.is_empty());
+        let split = matches!(self.mode, Mode::Insert(_)) && !self.config.paste_join;
+        let typed = if split {
+            lines.next().unwrap_or_default().to_string()
+        } else {
+            lines.by_ref().collect::<Vec<_>>().join(" ")
+        };
+
+        for c in typed.chars() {
+            match self.mode {
+                Mode::Insert(_) if !self.quit_prompt => {
+                    self.handle_insert_mode(InsertAction::Char(c))
+                }
+                Mode::Command(_) => self.handle_command_mode(InsertAction::Char(c))?,
+                Mode::Search(_) => self.handle_search_mode(InsertAction::Char(c)),
+                _ => return Ok(()),
+            }
+        }
+
+        let added = self.pasted_items(lines);
+        if split && !self.quit_prompt {
+            let (list, idx) = self.current_list_mut();
+            let below = *idx + 1;
+            list.splice(below..below, added.iter().cloned());
+            for item in &added {
+                self.run_hook("on_add", self.config.on_add.as_deref(), item);
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_search_mode(&mut self, action: InsertAction) {
+        let Mode::Search(buf) = &mut self.mode else {
+            unreachable!("Only called in search mode");
+        };
+
+        match action {
+            InsertAction::Char(c) => buf.push(c),
+            InsertAction::DeleteChar if buf.is_empty() => self.mode = Mode::Normal,
+            InsertAction::DeleteChar => pop_grapheme(buf),
+            InsertAction::DeleteWord => {
+                let start = word_start(buf, buf.graphemes(true).count());
+                buf.truncate(grapheme_to_byte(buf, start));
+            }
+            InsertAction::Cancel | InsertAction::Quit => self.mode = Mode::Normal,
+            InsertAction::Enter => {
+                let Mode::Search(query) = mem::replace(&mut self.mode, Mode::Normal) else {
+                    unreachable!();
+                };
+                // An empty search repeats the last one, like in vim
+                let Some(query) = Some(query)
+                    .filter(|query| !query.is_empty())
+                    .or_else(|| self.last_search.take())
+                else {
+                    return;
+                };
+                self.jump_to_match(&query, false, false);
+                self.last_search = Some(query);
+            }
+            InsertAction::Left
+            | InsertAction::Right
+            | InsertAction::WordLeft
+            | InsertAction::WordRight
+            | InsertAction::Home
+            | InsertAction::End
+            | InsertAction::PasteRegister => {}
+        }
+    }
+
+    /// Moves the cursor to the closest item of the current tab fuzzy matching
+    /// `query`, wrapping around the list. `skip_current` starts looking past
+    /// the item under the cursor, as `n` and `N` do. Otherwise the best match
+    /// wins, the closest one on a tie
+    fn jump_to_match(&mut self, query: &str, backwards: bool, skip_current: bool) {
+        let (items, _, idx) = self.column(self.curr_tab);
+        let mut matches = Self::search_order(items.len(), idx, backwards, skip_current)
+            .filter(|&i| !self.is_hidden(&items[i]))
+            .filter_map(|i| Some((i, fuzzy_match(self.search_text(&items[i]), query)?.score)));
+        let found = if skip_current {
+            matches.next()
+        } else {
+            matches.reduce(|best, other| if other.1 > best.1 { other } else { best })
+        }
+        .map(|(i, _)| i);
+
+        match found {
+            Some(i) => self.goto_list_pos(i),
+            None => self.status_msg = Some(format!("Pattern not found: {query}")),
+        }
+    }
+
+    /// Moves the cursor to the next item of the current tab `pred` holds for,
+    /// wrapping around the list, or says `not_found`
+    fn jump_to_next(&mut self, not_found: &str, pred: impl Fn(&str) -> bool) {
+        let (items, _, idx) = self.column(self.curr_tab);
+        let found = Self::search_order(items.len(), idx, false, true)
+            .find(|&i| !self.is_hidden(&items[i]) && pred(&items[i]));
+        match found {
+            Some(i) => self.goto_list_pos(i),
+            None => self.status_msg = Some(not_found.to_string()),
+        }
+    }
+
+    /// Indexes of a list of `len` items in the order a search visits them,
+    /// from `idx` or with `skip_current` the one after it, around to `idx`
+    fn search_order(
+        len: usize,
+        idx: usize,
+        backwards: bool,
+        skip_current: bool,
+    ) -> impl Iterator<Item = usize> {
+        let first = usize::from(skip_current);
+        (first..len + first).map(move |step| {
+            if backwards {
+                (idx + len - step % len) % len
+            } else {
+                (idx + step) % len
+            }
+        })
+    }
+
+    fn run_command(&mut self, cmd: &str) -> anyhow::Result<()> {
+        match cmd {
+            _ if self.config.read_only
+                && (matches!(cmd, "w" | "dedupe") || cmd.split(' ').next() == Some("due")) =>
+            {
+                self.status_msg = Some(READ_ONLY_MSG.into())
+            }
+            "w" => match self.save() {
+                Ok(()) => self.status_msg = Some(format!("Saved {}", self.file_path.display())),
+                Err(e) => self.status_msg = Some(format!("Failed to save: {e}")),
+            },
+            // Quitting always saves unless told otherwise
+            "q" | "wq" | "x" if self.empties_file() => {
+                self.mode = Mode::Confirm(Confirm::SaveEmpty)
+            }
+            "q" | "wq" | "x" => anyhow::bail!(globals::BREAK),
+            "q!" => anyhow::bail!(globals::NO_SAVE),
+            "due" => self.set_due(None),
+            "e" => self.status_msg = Some("Expected a file to open".into()),
+            _ if cmd.starts_with("e ") => {
+                let path = cmd["e ".len()..].trim();
+                if let Err(e) = self.open_file(Path::new(path)) {
+                    self.status_msg = Some(format!("Couldn't open {path}: {e:#}"));
+                }
+            }
+            "dedupe" => self.dedupe_list(),
+            "tag" => self.filter = None,
+            _ if cmd.starts_with("tag ") => {
+                let tag = cmd["tag ".len()..].trim();
+                let tag = tag.strip_prefix('#').unwrap_or(tag);
+                if tag.is_empty() {
+                    self.filter = None;
+                } else if is_tag(&format!("#{tag}")) {
+                    self.filter = Some(Filter::Tag(tag.to_lowercase()));
+                } else {
+                    self.status_msg = Some(format!("Not a tag: {tag}"));
+                }
+            }
+            _ if cmd.starts_with("due ") => {
+                let date = cmd["due ".len()..].trim();
+                if is_date(date) {
+                    self.set_due(Some(date));
+                } else {
+                    self.status_msg = Some(format!("Expected a YYYY-MM-DD date, got {date:?}"));
+                }
+            }
+            "" => {}
+            _ => self.status_msg = Some(format!("Not a command: {cmd}")),
+        }
+        Ok(())
+    }
+
+    /// Removes later copies of items already in the current list
+    fn dedupe_list(&mut self) {
+        let mut list = self.current_list_mut().0.clone();
+        let removed = dedupe(&mut list);
+        if removed > 0 {
+            self.checkpoint();
+            *self.current_list_mut().0 = list;
+        }
+        self.status_msg = Some(format!("Removed {removed} duplicate(s)"));
+    }
+
+    /// Warns, without refusing it, when the item just typed is already in its list
+    fn warn_duplicate(&mut self) {
+        let (items, _, idx) = self.column(self.curr_tab);
+        let Some(entry) = items.get(idx) else {
+            return;
+        };
+        let text = entry_text(entry).trim();
+        if text.is_empty() || heading_text(entry).is_some() {
+            return;
+        }
+        // Subtasks are only compared with those of the same item, like `dedupe` does
+        let parent = parent_of(items, idx);
+        let is_subtask = subtask_text(entry).is_some();
+        let twice = (items.iter().enumerate()).any(|(i, other)| {
+            i != idx
+                && heading_text(other).is_none()
+                && subtask_text(other).is_some() == is_subtask
+                && (!is_subtask || parent_of(items, i) == parent)
+                && entry_text(other).trim() == text
+        });
+        if twice {
+            self.status_msg = Some(format!("Already in the list: {text}"));
+        }
+    }
+
+    /// Sets or clears the due date of the item under the cursor
+    fn set_due(&mut self, date: Option<&str>) {
+        if self.get_current_buffer().is_none() || self.on_heading() {
+            return;
+        }
+
+        self.checkpoint();
+        let (list, idx) = self.current_list_mut();
+        list[*idx] = with_due(&list[*idx], date);
+    }
+
+    fn handle_help_mode(&mut self, action: Action) {
+        match action {
+            Action::SaveQuit | Action::NoSaveQuit => self.mode = Mode::Normal,
+            _ => {}
         }
     }
 
     fn handle_insert_mode(&mut self, code: InsertAction) {
+        // Borrows the list field directly, the mode is swapped below
         let buf = match self.curr_tab {
-            Tab::Todos => self.todos.get_mut(self.todos_idx).unwrap(),
-            Tab::Dones => self.dones.get_mut(self.dones_idx).unwrap(),
+            Tab::Todos => self.todos.get_mut(self.todos_idx),
+            Tab::Dones => self.dones.get_mut(self.dones_idx),
+            Tab::Someday => self.someday.get_mut(self.someday_idx),
+        };
+        // The item being typed is gone, there's nothing left to edit
+        let Some(buf) = buf else {
+            self.mode = Mode::Normal;
+            return;
+        };
+
+        // The cursor counts grapheme clusters, never landing inside one. It
+        // never moves before or erases the mark that makes an entry a heading
+        // or a subtask
+        let start = buf[..buf.len() - entry_text(buf).len()]
+            .graphemes(true)
+            .count();
+        let len = buf.graphemes(true).count();
+        let cursor = &mut self.edit_cursor;
+        *cursor = (*cursor).clamp(start, len);
+
+        match code {
+            InsertAction::Enter => self.disable_insert_mode(),
+            // Cancel operation and not save
+            InsertAction::Cancel => {
+                match mem::replace(&mut self.mode, Mode::Normal) {
+                    Mode::Insert(InsertMode::Edit(snap)) => *buf = snap,
+                    Mode::Insert(InsertMode::New) => _ = self.handle_delete(1),
+                    _ => unreachable!(),
+                };
+                self.disable_insert_mode();
+            }
+            // Never drop an in-progress edit silently, ask what to do with it
+            InsertAction::Quit => {
+                self.quit_prompt = true;
+                self.status_msg = Some(
+                    "Quit: [c]ommit edit / [d]iscard edit / any other key to keep editing".into(),
+                );
+            }
+            // Pasted tabs and other control characters would end up on the
+            // screen and in the file as they are
+            InsertAction::Char(c) if c.is_control() && c != '\t' => {}
+            InsertAction::Char(c) => {
+                let c = if c == '\t' { ' ' } else { c };
+                // A combining mark joins the cluster before it instead of adding one
+                let at = grapheme_to_byte(buf, *cursor);
+                buf.insert(at, c);
+                *cursor = buf[..at + c.len_utf8()].graphemes(true).count();
+            }
+            InsertAction::DeleteChar if *cursor > start => {
+                *cursor -= 1;
+                let at = grapheme_to_byte(buf, *cursor);
+                buf.replace_range(at..grapheme_to_byte(buf, *cursor + 1), "");
+            }
+            InsertAction::DeleteChar => {}
+            InsertAction::DeleteWord => {
+                let from = word_start(buf, *cursor).max(start);
+                let at = grapheme_to_byte(buf, from);
+                buf.replace_range(at..grapheme_to_byte(buf, *cursor), "");
+                *cursor = from;
+            }
+            InsertAction::Left => *cursor = cursor.saturating_sub(1).max(start),
+            InsertAction::Right => *cursor = (*cursor + 1).min(len),
+            InsertAction::WordLeft => *cursor = word_start(buf, *cursor).max(start),
+            InsertAction::WordRight => *cursor = word_end(buf, *cursor),
+            InsertAction::Home => *cursor = start,
+            InsertAction::End => *cursor = len,
+            // The latest yanked or deleted items, joined into one line
+            InsertAction::PasteRegister => {
+                let Some(items) = self.registers.get(&globals::UNNAMED_REGISTER) else {
+                    return;
+                };
+                let text = items
+                    .iter()
+                    .map(|item| entry_text(item))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let at = grapheme_to_byte(buf, *cursor);
+                buf.insert_str(at, &text);
+                *cursor = buf[..at + text.len()].graphemes(true).count();
+            }
+        }
+    }
+
+    fn start_insert_mode(&mut self, direction: KeyCode) {
+        self.mode = Mode::Insert(InsertMode::New);
+
+        let (list, idx) = self.current_list_mut();
+
+        let insert_idx = Self::insert_position(list.len(), *idx, direction);
+        // Typed next to a subtask, or above the first one of an item, it's one too
+        let among_subtasks = [*idx, insert_idx].iter().any(|&i| {
+            list.get(i)
+                .is_some_and(|entry| subtask_text(entry).is_some())
+        });
+        let entry = if among_subtasks {
+            SUBTASK_MARK.to_string()
+        } else {
+            String::new()
+        };
+        // Saved to the file of the item it's typed next to
+        let source = list.get(*idx).map_or(0, |entry| entry_source(entry));
+        let entry = with_source(&entry, source);
+        let cursor = entry.graphemes(true).count();
+        list.insert(insert_idx, entry);
+        *idx = insert_idx;
+        self.edit_cursor = cursor;
+    }
+
+    /// Inserts an empty heading below the cursor and starts editing it
+    fn start_heading_insert(&mut self) {
+        // Someday items are never grouped
+        if matches!(self.curr_tab, Tab::Someday) {
+            return;
+        }
+
+        self.start_insert_mode(KeyCode::Down);
+        let (list, idx) = self.current_list_mut();
+        // Headings are never subtasks
+        let source = entry_source(&list[*idx]);
+        list[*idx] = with_source(&HEADING_MARK.to_string(), source);
+        self.edit_cursor = list[*idx].graphemes(true).count();
+    }
+
+    fn handle_yank(&mut self) {
+        let Some(item) = self.get_current_buffer().cloned() else {
+            return;
+        };
+        self.store_in_register(vec![item]);
+    }
+
+    /// Stores a block of items in the selected register. The `+` register is
+    /// the system clipboard, which gets the items' text one per line
+    fn store_in_register(&mut self, items: Vec<String>) {
+        match self.active_register.take() {
+            Some(globals::CLIPBOARD_REGISTER) => {
+                let lines: Vec<_> = items.iter().map(|item| entry_text(item)).collect();
+                match copy_to_clipboard(&lines.join("\n")) {
+                    Ok(()) => self.status_msg = Some("Copied to the clipboard".into()),
+                    Err(e) => self.status_msg = Some(format!("Clipboard: {e}")),
+                }
+            }
+            Some(name) => _ = self.registers.insert(name, items.clone()),
+            None => {}
+        }
+        // The unnamed register always holds the latest block
+        self.registers.insert(globals::UNNAMED_REGISTER, items);
+    }
+
+    fn handle_paste(&mut self, direction: KeyCode) {
+        let name = self
+            .active_register
+            .take()
+            .unwrap_or(globals::UNNAMED_REGISTER);
+        let items = if name == globals::CLIPBOARD_REGISTER {
+            // Each non-blank line of the clipboard becomes an item
+            match paste_from_clipboard() {
+                Ok(text) => self.pasted_items(text.lines()),
+                Err(e) => {
+                    self.status_msg = Some(format!("Clipboard: {e}"));
+                    return;
+                }
+            }
+        } else {
+            let Some(items) = self.registers.get(&name).cloned() else {
+                return;
+            };
+            items
+        };
+        if items.is_empty() {
+            return;
+        }
+
+        let (list, idx) = self.current_list_mut();
+        let insert_idx = Self::insert_position(list.len(), *idx, direction);
+        list.splice(insert_idx..insert_idx, items);
+        *idx = insert_idx;
+    }
+
+    /// Pasted `lines` as new items, blank ones left out. They're cleaned like
+    /// loaded items, dated with `track_age`, and go to the file of the item
+    /// under the cursor
+    fn pasted_items<'a>(&self, lines: impl Iterator<Item = &'a str>) -> Vec<String> {
+        let source = self
+            .get_current_buffer()
+            .map_or(0, |entry| entry_source(entry));
+        lines
+            .map(|line| clean_text(line).trim().to_string())
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let line = if self.config.track_age {
+                    stamp_added(&line)
+                } else {
+                    line
+                };
+                with_source(&line, source)
+            })
+            .collect()
+    }
+
+    /// Edits the item under the cursor, starting from an empty buffer if `clear`.
+    /// Cancelling restores the original text either way
+    fn start_edit_mode(&mut self, clear: bool) {
+        let (list, idx) = self.current_list_mut();
+        let Some(buf) = list.get_mut(*idx) else {
+            return;
+        };
+
+        let snap = if clear {
+            // A cleared heading or subtask stays one
+            let mark = buf.len() - entry_text(buf).len();
+            let blank = buf[..mark].to_string();
+            mem::replace(buf, blank)
+        } else {
+            buf.clone()
+        };
+        self.edit_cursor = buf.graphemes(true).count();
+        self.mode = Mode::Insert(InsertMode::Edit(snap))
+    }
+
+    fn disable_insert_mode(&mut self) {
+        if self.config.warn_duplicates && matches!(self.mode, Mode::Insert(_)) {
+            self.warn_duplicate();
+        }
+        if let Mode::Insert(InsertMode::New) = self.mode {
+            if self.config.track_age {
+                let (list, idx) = self.current_list_mut();
+                if let Some(item) = list.get_mut(*idx) {
+                    *item = stamp_added(item);
+                }
+            }
+            if let Some(item) = self.get_current_buffer() {
+                self.run_hook("on_add", self.config.on_add.as_deref(), item);
+            }
+        }
+        self.mode = Mode::Normal;
+    }
+}
+
+/// Utilities / Internal Helpers
+impl App {
+    fn save(&mut self) -> io::Result<()> {
+        self.layout = self.write_file(&self.file_path, Some(0), self.config.max_length)?;
+        for source in 1..=self.extra_files.len() {
+            let path = self.extra_files[source - 1].path.clone();
+            let layout = self.write_file(&path, Some(source), self.config.max_length)?;
+            self.extra_files[source - 1].layout = layout;
+        }
+        self.file_mtime = modified(&self.file_path);
+        self.dirty = false;
+        self.last_save = Instant::now();
+        self.edits_since_save = 0;
+        if self.config.save_log {
+            self.log_save();
+        }
+        Ok(())
+    }
+
+    /// Appends when the file was saved and how many items it holds to
+    /// `<file>.save.log`
+    fn log_save(&mut self) {
+        let mut log_path = self.file_path.clone().into_os_string();
+        log_path.push(".save.log");
+        let line = format!(
+            "{} saved {} todos, {} dones, {} someday, {} archived",
+            timestamp(),
+            self.todos.len(),
+            self.dones.len(),
+            self.someday.len(),
+            self.archived_todos.len() + self.archived_dones.len()
+        );
+        if let Err(e) = append_line(log_path, &line) {
+            self.status_msg = Some(format!("Failed to log save: {e}"));
+        }
+    }
+
+    /// Whether unsaved changes are old or many enough to be saved on their own
+    fn autosave_due(&self) -> bool {
+        let elapsed = self
+            .config
+            .autosave_interval
+            .is_some_and(|interval| self.last_save.elapsed() >= interval);
+        let edits = self
+            .config
+            .autosave_edits
+            .is_some_and(|edits| self.edits_since_save >= edits);
+        self.dirty && (elapsed || edits) && !self.config.read_only
+    }
+
+    /// Saves through the same temp file as quitting, returns whether there is
+    /// a failure to show. A failed save waits for the next turn before retrying
+    fn autosave(&mut self) -> bool {
+        // Left for quitting to ask about, until the next turn
+        if self.empties_file() {
+            self.last_save = Instant::now();
+            self.edits_since_save = 0;
+            return false;
+        }
+        let Err(e) = self.save() else {
+            return false;
+        };
+        self.last_save = Instant::now();
+        self.edits_since_save = 0;
+        self.status_msg = Some(format!("Autosave failed: {e}"));
+        true
+    }
+
+    /// Whether saving would leave a file that has items on disk without any,
+    /// which is almost always a mistake unless `allow_empty_save` says so
+    fn empties_file(&self) -> bool {
+        if self.config.allow_empty_save || self.config.read_only {
+            return false;
+        }
+        let lists = [
+            &self.todos,
+            &self.dones,
+            &self.someday,
+            &self.archived_todos,
+            &self.archived_dones,
+        ];
+        let is_item = |item: &String| !entry_text(item).trim().is_empty();
+        let paths = iter::once(&self.file_path).chain(self.extra_files.iter().map(|f| &f.path));
+        paths.enumerate().any(|(source, path)| {
+            let kept = lists.iter().flat_map(|items| items.iter());
+            if kept
+                .filter(|item| entry_source(item) == source)
+                .any(is_item)
+            {
+                return false;
+            }
+            get_todos_dones(path, self.config.format, &self.config.prefixes).is_ok_and(|file| {
+                let sections = file.sections();
+                sections.iter().any(|(_, items)| items.iter().any(is_item))
+            })
+        })
+    }
+
+    /// Saves the lists next to where tick was started, or else in the temp
+    /// directory, for when the file itself can't be written. An existing file
+    /// there is never overwritten
+    fn save_fallback(&self) -> anyhow::Result<PathBuf> {
+        let mut name = self
+            .file_path
+            .file_name()
+            .unwrap_or_default()
+            .to_os_string();
+        name.push(".recovered");
+
+        let mut errors = Vec::new();
+        for dir in [env::current_dir()?, env::temp_dir()] {
+            let path = dir.join(&name);
+            if path.exists() {
+                errors.push(format!("{} already exists", path.display()));
+                continue;
+            }
+            // Everything is kept here, however long, the items of every file
+            // open together
+            match self.write_file(&path, None, None) {
+                Ok(_) => return Ok(path),
+                Err(e) => errors.push(format!("{}: {e}", path.display())),
+            }
+        }
+        anyhow::bail!(
+            "Couldn't save anywhere, changes are lost ({})",
+            errors.join("; ")
+        )
+    }
+
+    /// Saves the lists to `path`, returns the layout of what was written.
+    /// With `source`, only the items of that file are saved, over its layout,
+    /// and otherwise every item over the first file's.
+    /// Items whose text, dates left out, is longer than `max_length`
+    /// characters are cut off, or else refused with the list of them, as
+    /// `max_length_truncate` says. The lists themselves are left alone
+    fn write_file(
+        &self,
+        path: &Path,
+        source: Option<usize>,
+        max_length: Option<usize>,
+    ) -> io::Result<Vec<Line>> {
+        let lists: [(Kind, &str, &[String]); 5] = [
+            (Kind::Todo, "todo", &self.todos),
+            (Kind::Done, "done", &self.dones),
+            (Kind::Someday, "someday", &self.someday),
+            (Kind::ArchivedTodo, "archived todo", &self.archived_todos),
+            (Kind::ArchivedDone, "archived done", &self.archived_dones),
+        ];
+        let mut sections = lists.map(|(kind, _, items)| (kind, Self::items_of(items, source)));
+        let layout = match source {
+            Some(source @ 1..) => &self.extra_files[source - 1].layout,
+            _ => &self.layout,
+        };
+        if let Some(max) = max_length {
+            let too_long = |entry: &&String| Self::length_checked(entry).chars().count() > max;
+            // Positions in the whole lists, as the absolute item numbers show them
+            let base = self.number_base();
+            let too_long: Vec<String> = lists
+                .iter()
+                .flat_map(|(_, name, items)| {
+                    let saved = items.iter().enumerate().filter(|(_, entry)| {
+                        source.is_none_or(|source| entry_source(entry) == source)
+                    });
+                    saved
+                        .filter(|(_, entry)| too_long(entry))
+                        .map(move |(idx, _)| format!("{name} {}", idx + base))
+                })
+                .collect();
+            if !too_long.is_empty() {
+                if !self.config.max_length_truncate {
+                    return Err(io::Error::other(format!(
+                        "items longer than {max} characters: {}",
+                        too_long.join(", ")
+                    )));
+                }
+                for (_, items) in &mut sections {
+                    for entry in items.to_mut() {
+                        *entry = Self::truncate_text(entry, max);
+                    }
+                }
+            }
+        }
+
+        let sections = sections.each_ref().map(|(kind, items)| (*kind, &**items));
+        let Config {
+            format,
+            prefixes,
+            backup,
+            ..
+        } = &self.config;
+        let mut backend = FileBackend {
+            path: path.to_path_buf(),
+            backup: *backup,
+        };
+        backend.save(*format, prefixes, layout, &sections)
+    }
+
+    /// What `max_length` measures of an entry: its text without the marks
+    /// kept in entries or the dates at its end
+    fn length_checked(entry: &str) -> &str {
+        split_added(entry_text(entry)).0
+    }
+
+    /// `entry` with its text cut to `max` characters, its marks and dates kept
+    fn truncate_text(entry: &str, max: usize) -> String {
+        let body = entry_text(entry);
+        let text = Self::length_checked(entry);
+        let marks = &entry[..entry.len() - body.len()];
+        let dates = &body[text.len()..];
+        format!("{marks}{}{dates}", truncate_chars(text, max))
+    }
+
+    /// Items of the file numbered `source`, or of every file, without the
+    /// mark of the file they came from
+    fn items_of(items: &[String], source: Option<usize>) -> Cow<'_, [String]> {
+        if items.iter().all(|entry| entry_source(entry) == 0) && source.is_none_or(|s| s == 0) {
+            return Cow::Borrowed(items);
+        }
+        let items = items.iter();
+        items
+            .filter(|entry| source.is_none_or(|s| entry_source(entry) == s))
+            .map(|entry| with_source(entry, 0))
+            .collect()
+    }
+
+    /// Appends items piped in on stdin to the loaded ones. Lines in the file
+    /// format go to their list, any other line becomes a todo
+    pub fn add_lines(&mut self, lines: &[String]) {
+        if self.config.read_only {
+            return;
+        }
+        let mut piped = TodoFile::default();
+        for line in lines.iter().filter(|line| !line.trim().is_empty()) {
+            let mut item =
+                (self.config.format.parse(line, &self.config.prefixes)).unwrap_or_else(|_| Item {
+                    kind: Kind::Todo,
+                    text: line.clone(),
+                });
+            if self.config.track_age {
+                item.text = stamp_added(&item.text);
+            }
+            piped.push(item);
+        }
+
+        let added = piped.todos.len()
+            + piped.dones.len()
+            + piped.someday.len()
+            + piped.archived_todos.len()
+            + piped.archived_dones.len();
+        if added == 0 {
+            return;
+        }
+        self.todos.extend(piped.todos);
+        self.dones.extend(piped.dones);
+        self.someday.extend(piped.someday);
+        self.archived_todos.extend(piped.archived_todos);
+        self.archived_dones.extend(piped.archived_dones);
+        self.dirty = true;
+        self.status_msg = Some(format!("Added {added} item(s) from stdin"));
+    }
+
+    /// Replaces the lists with what's on disk. Only the first file is read
+    /// again, the items of the others stay as they are
+    fn reload(&mut self) -> anyhow::Result<()> {
+        let loaded = get_todos_dones(&self.file_path, self.config.format, &self.config.prefixes)?;
+        let others = |items: &[String]| -> Vec<String> {
+            let items = items.iter();
+            items.filter(|e| entry_source(e) > 0).cloned().collect()
+        };
+        let others = [
+            others(&self.todos),
+            others(&self.dones),
+            others(&self.someday),
+            others(&self.archived_todos),
+            others(&self.archived_dones),
+        ];
+        self.replace_lists(loaded);
+        let [todos, dones, someday, archived_todos, archived_dones] = others;
+        self.todos.extend(todos);
+        self.dones.extend(dones);
+        self.someday.extend(someday);
+        self.archived_todos.extend(archived_todos);
+        self.archived_dones.extend(archived_dones);
+        Ok(())
+    }
+
+    /// Saves the current file and switches to the one at `path`, resolved
+    /// like the path given on the command line. The new file is loaded and
+    /// locked first, so on any error the current one stays open untouched
+    fn open_file(&mut self, path: &Path) -> anyhow::Result<()> {
+        let file_path = resolve_path(Some(path.to_path_buf()), self.config.format)?;
+        if file_path == self.file_path {
+            self.status_msg = Some(format!("{} is already open", file_path.display()));
+            return Ok(());
+        }
+        let loaded = get_todos_dones(&file_path, self.config.format, &self.config.prefixes)?;
+        let lock = if self.config.read_only {
+            None
+        } else {
+            FileLock::acquire(&file_path)?
+        };
+        if self.dirty && !self.config.read_only {
+            self.save()
+                .with_context(|| format!("saving {} failed", self.file_path.display()))?;
+        }
+        let mut ui_state = UiState::load();
+        if let Ok(path) = fs::canonicalize(&self.file_path) {
+            self.store_sort_modes(&mut ui_state, &path);
+            // Losing the sorting is no reason to stay on this file
+            _ = ui_state.save();
+        }
+
+        // The old locks go away as they're replaced, along with the files
+        // opened next to the old one
+        self._lock = lock;
+        self.extra_files.clear();
+        self.file_path = file_path;
+        self.status_msg = Some(format!("Opened {}", self.file_path.display()));
+        self.replace_lists(loaded);
+        if let Err(e) = check_writable(&self.file_path)
+            && !self.config.read_only
+        {
+            self.status_msg = Some(format!("Can't save: {e}"));
+        }
+
+        // Positions and history of the previous file mean nothing here
+        self.todos_idx = 0;
+        self.dones_idx = 0;
+        self.someday_idx = 0;
+        self.todos_scroll = 0;
+        self.dones_scroll = 0;
+        self.someday_scroll = 0;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.filter = None;
+        self.last_save = Instant::now();
+        self.edits_since_save = 0;
+        self.sort_modes = [SortMode::Insertion; 3];
+        if let Ok(path) = fs::canonicalize(&self.file_path) {
+            self.restore_sort_modes(&ui_state, &path);
+        }
+        Ok(())
+    }
+
+    /// Puts freshly loaded lists in place of the current ones
+    fn replace_lists(&mut self, loaded: TodoFile) {
+        if let Some(msg) = loaded.load_msg() {
+            self.status_msg = Some(msg);
+        }
+        let migrated = loaded.migrated > 0;
+        self.todos = loaded.todos;
+        self.dones = loaded.dones;
+        self.someday = loaded.someday;
+        self.archived_todos = loaded.archived_todos;
+        self.archived_dones = loaded.archived_dones;
+        self.layout = loaded.layout;
+        self.collapsed.clear();
+
+        self.file_mtime = modified(&self.file_path);
+        self.dirty = migrated;
+    }
+
+    fn get_current_buffer(&self) -> Option<&String> {
+        let (items, _, idx) = self.column(self.curr_tab);
+        items.get(idx)
+    }
+
+    /// Whether `action` is turned down because the file is opened read-only,
+    /// saying so on the status line
+    fn refuse_read_only(&mut self, action: Action) -> bool {
+        let refused = self.config.read_only
+            && (action.is_mutating()
+                || matches!(
+                    action,
+                    Action::SetDue | Action::ClearList | Action::DeleteOperator
+                ));
+        if refused {
+            self.status_msg = Some(READ_ONLY_MSG.into());
+        }
+        refused
+    }
+
+    /// Whether `action` is turned down because of the filter: visual mode
+    /// would span hidden items, and with nothing shown the cursor rests on
+    /// a hidden item that must stay untouched
+    fn refuse_filtered(&mut self, action: Action) -> bool {
+        let Some(filter) = &self.filter else {
+            return false;
+        };
+        let on_hidden = self
+            .get_current_buffer()
+            .is_some_and(|entry| self.is_hidden(entry));
+        let refused = match action {
+            Action::Visual => Some("Visual mode is off while filtering".to_string()),
+            // Adding items or working on the whole list doesn't need one
+            Action::Insert(_)
+            | Action::InsertHeading
+            | Action::Paste(_)
+            | Action::PasteClipboard
+            | Action::Sort
+            | Action::SortPriority
+            | Action::ExternalEdit => None,
+            _ if on_hidden
+                && (action.is_mutating()
+                    || matches!(
+                        action,
+                        Action::SetDue | Action::Yank | Action::YankClipboard
+                    )) =>
+            {
+                Some(match filter {
+                    Filter::Tag(tag) => format!("No item tagged #{tag}"),
+                    Filter::Due(_) => "No item due by today".to_string(),
+                })
+            }
+            _ => None,
+        };
+        match refused {
+            Some(msg) => {
+                self.status_msg = Some(msg);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the filter hides `entry` from its list. Headings go with it,
+    /// while an item being typed stays until it's committed. The due filter
+    /// only narrows the todos
+    fn is_hidden(&self, entry: &String) -> bool {
+        let Some(filter) = &self.filter else {
+            return false;
+        };
+        if matches!(self.mode, Mode::Archived) || self.is_editing(entry) {
+            return false;
+        }
+        match filter {
+            Filter::Tag(tag) => heading_text(entry).is_some() || !has_tag(entry, tag),
+            Filter::Due(_) if !self.todos.as_ptr_range().contains(&ptr::from_ref(entry)) => false,
+            Filter::Due(date) => {
+                heading_text(entry).is_some()
+                    || split_due(entry).1.is_none_or(|due| due > date.as_str())
+            }
+        }
+    }
+
+    /// Whether the subtask at `idx` is hidden by collapsing the item it's
+    /// under. One being typed stays
+    fn is_folded(&self, items: &[String], idx: usize) -> bool {
+        !self.is_editing(&items[idx])
+            && parent_of(items, idx).is_some_and(|parent| self.collapsed.contains(&items[parent]))
+    }
+
+    /// Flashes what happened to `item` on the status line, cut to fit
+    fn report_move(&mut self, verb: &str, item: &str) {
+        let (text, _) = split_added(entry_text(item));
+        let room = (self.term_size.0 as usize).saturating_sub(verb.len() + 4);
+        self.flash(format!(
+            "{verb}: \"{}\"",
+            truncate_to_fit(text.trim(), room)
+        ));
+    }
+
+    /// Shows `msg` on the status line until the next key, or for
+    /// `STATUS_FLASH` at most
+    fn flash(&mut self, msg: String) {
+        self.status_expiry = Some((Instant::now() + STATUS_FLASH, msg.clone()));
+        self.status_msg = Some(msg);
+    }
+
+    fn on_heading(&self) -> bool {
+        self.get_current_buffer()
+            .is_some_and(|entry| heading_text(entry).is_some())
+    }
+
+    fn run_hook(&self, name: &'static str, cmd: Option<&str>, item: &str) {
+        if let Some(cmd) = cmd {
+            hooks::spawn(name, cmd, entry_text(item), self.hook_reports.0.clone());
+        }
+    }
+
+    /// Records the current lists before a change so it can be undone
+    fn checkpoint(&mut self) {
+        self.dirty = true;
+        self.edits_since_save += 1;
+        if self.undo_stack.len() == UNDO_LIMIT {
+            self.undo_stack.pop_front();
+        }
+        let snapshot = self.snapshot();
+        self.undo_stack.push_back(snapshot);
+        self.redo_stack.clear();
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            todos: self.todos.clone(),
+            dones: self.dones.clone(),
+            someday: self.someday.clone(),
+            archived_todos: self.archived_todos.clone(),
+            archived_dones: self.archived_dones.clone(),
+            todos_idx: self.todos_idx,
+            dones_idx: self.dones_idx,
+            someday_idx: self.someday_idx,
+            curr_tab: self.curr_tab,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.todos = snapshot.todos;
+        self.dones = snapshot.dones;
+        self.someday = snapshot.someday;
+        self.archived_todos = snapshot.archived_todos;
+        self.archived_dones = snapshot.archived_dones;
+        self.todos_idx = snapshot.todos_idx;
+        self.dones_idx = snapshot.dones_idx;
+        self.someday_idx = snapshot.someday_idx;
+        self.curr_tab = snapshot.curr_tab;
+        self.dirty = true;
+    }
+
+    /// The active list and its cursor
+    fn current_list_mut(&mut self) -> (&mut Vec<String>, &mut usize) {
+        match self.curr_tab {
+            Tab::Todos => (&mut self.todos, &mut self.todos_idx),
+            Tab::Dones => (&mut self.dones, &mut self.dones_idx),
+            Tab::Someday => (&mut self.someday, &mut self.someday_idx),
+        }
+    }
+
+    /// Dones carry the day they were completed, any other list drops it. A
+    /// subtask moved on its own leaves the item it was under
+    fn restamp(item: String, to: Tab) -> String {
+        if heading_text(&item).is_some() {
+            return item;
+        }
+        let text = entry_text(&item);
+        let text = match to {
+            Tab::Dones => with_done(text, Some(&today())),
+            Tab::Todos | Tab::Someday => with_done(text, None),
+        };
+        with_source(&text, entry_source(&item))
+    }
+
+    /// The item at `idx` taken out of `list` along with its subtasks. A
+    /// subtask taken on its own leaves the item it was under
+    fn take_block(list: &mut Vec<String>, idx: usize) -> Vec<String> {
+        let item = list.remove(idx);
+        if let Some(text) = subtask_text(&item) {
+            return vec![text.to_string()];
+        }
+        let mut block = vec![item];
+        block.extend(Self::detach_subtasks(list, idx, true));
+        block
+    }
+
+    /// Subtasks at `at` in `list`, right where the item they were under was
+    /// taken out. With `take` they're taken out too, or else they become items
+    /// of their own instead of going under the item above
+    fn detach_subtasks(list: &mut Vec<String>, at: usize, take: bool) -> Vec<String> {
+        let rest = &list[at.min(list.len())..];
+        let count = rest
+            .iter()
+            .take_while(|e| subtask_text(e).is_some())
+            .count();
+        if take {
+            return list.drain(at..at + count).collect();
+        }
+        for entry in &mut list[at..at + count] {
+            *entry = subtask_text(entry).unwrap_or(entry).to_string();
+        }
+        Vec::new()
+    }
+
+    /// Items selected in visual mode, from the anchor to the cursor
+    fn visual_range(&self) -> Option<Range<usize>> {
+        let Mode::Visual(anchor) = self.mode else {
+            return None;
+        };
+        let (items, _, idx) = self.column(self.curr_tab);
+        let last = items.len().checked_sub(1)?;
+        let (start, end) = (anchor.min(idx), anchor.max(idx));
+        Some(start.min(last)..end.min(last) + 1)
+    }
+
+    /// Index at which a new item lands above (`Up`) or below (`Down`) the cursor
+    fn insert_position(len: usize, idx: usize, direction: KeyCode) -> usize {
+        // On an empty list both directions insert at index 0
+        if len == 0 {
+            return 0;
+        }
+
+        match direction {
+            KeyCode::Up => idx.min(len - 1),
+            KeyCode::Down => idx.min(len - 1) + 1,
+            _ => unreachable!(),
+        }
+    }
+
+    /// The todos and dones tabs in the order of their columns, left to right
+    fn column_tabs(&self) -> [Tab; 2] {
+        if self.config.swap_columns {
+            [Tab::Dones, Tab::Todos]
+        } else {
+            [Tab::Todos, Tab::Dones]
+        }
+    }
+
+    /// Starting column and width available to the items of `tab`
+    fn column_geometry(&self, tab: Tab, cols: u16) -> (u16, usize) {
+        let col_mid = cols / 2;
+        let full_width = (0, cols.saturating_sub(1) as usize);
+        match tab {
+            _ if self.is_single_column(cols) => full_width,
+            Tab::Someday => full_width,
+            tab if tab == self.column_tabs()[0] => (0, col_mid.saturating_sub(1) as usize),
+            _ => (col_mid, col_mid as usize),
+        }
+    }
+
+    /// Whether only the active tab is drawn, as asked for or because the
+    /// terminal is too narrow for two columns
+    fn is_single_column(&self, cols: u16) -> bool {
+        self.single_column || cols < MIN_SPLIT_COLS
+    }
+
+    /// Splits the item at `idx` into the screen lines it occupies
+    fn wrap_item(
+        &self,
+        items: &[String],
+        idx: usize,
+        line_begin: &str,
+        max_width: usize,
+    ) -> Vec<String> {
+        let (full_line, offset) = self.full_line(items, idx, line_begin);
+
+        // Keep clear of the date drawn at the right of the first line
+        let max_width = match self.date_label(&items[idx]) {
+            Some(label) => max_width.saturating_sub(text_width(&label) + 1).max(1),
+            None => max_width,
         };
 
-        match code {
-            InsertAction::Enter => self.disable_insert_mode(),
-            // Cancel operation and not save
-            InsertAction::Cancel => {
-                match mem::replace(&mut self.mode, Mode::Normal) {
-                    Mode::Insert(InsertMode::Edit(snap)) => *buf = snap,
-                    Mode::Insert(InsertMode::New) => self.handle_delete(),
-                    _ => unreachable!(),
+        // The item being typed wraps so the cursor can reach all of it
+        if self.truncate && !self.is_editing(&items[idx]) {
+            return vec![truncate_to_fit(&full_line, max_width)];
+        }
+
+        // Wrapping is measured char by char, so reuse it across frames
+        let key = (full_line, max_width, offset);
+        if let Some(lines) = self.wrap_cache.borrow().get(&key) {
+            return lines.clone();
+        }
+
+        let (first_line, rest_lines) = split_to_fit(&key.0, max_width, offset);
+        let padding = " ".repeat(offset);
+        let mut lines = vec![first_line.to_string()];
+        lines.extend(rest_lines.iter().map(|line| format!("{}{}", padding, line)));
+
+        let mut cache = self.wrap_cache.borrow_mut();
+        if cache.len() >= WRAP_CACHE_LIMIT {
+            cache.clear();
+        }
+        cache.insert(key, lines.clone());
+        lines
+    }
+
+    /// Unwrapped line of an item, with its checkbox or number, and the indent
+    /// of its continuation lines
+    fn full_line(&self, items: &[String], idx: usize, line_begin: &str) -> (String, usize) {
+        // Width of the largest number, which relative distances never exceed
+        // either. Numbers take at least the room of the checkbox, so the text
+        // stays put when they're turned on or off
+        let base = self.number_base();
+        let largest = (items.len() + base).saturating_sub(1).to_string().len();
+        let num_width = largest.max(self.config.number_width.unwrap_or(0));
+        let label_width = (num_width + 1).max(text_width(line_begin));
+
+        let (text, _) = self.shown_date(&items[idx]);
+        let source = self.source_label(text);
+
+        // Headings span the column, without checkbox or number
+        match heading_text(text) {
+            Some(_) => (format!("{source}{}", entry_text(text)), 0),
+            None => {
+                let indent = match subtask_text(text) {
+                    Some(_) => SUBTASK_INDENT,
+                    None => "",
                 };
-                self.disable_insert_mode();
+                let text = entry_text(text);
+                // A collapsed item says how many subtasks it hides
+                let hidden = subtask_count(items, idx);
+                let folded = if hidden > 0
+                    && self.collapsed.contains(&items[idx])
+                    && !self.is_editing(&items[idx])
+                {
+                    format!(" [+{hidden}]")
+                } else {
+                    String::new()
+                };
+                let selected = self.selected_in(items);
+                let number = match self.show_number {
+                    Numbers::Off => None,
+                    Numbers::Relative if idx != selected => Some(idx.abs_diff(selected)),
+                    Numbers::Absolute | Numbers::Relative => Some(idx + base),
+                };
+                let line_label = match (number, self.config.number_align) {
+                    (None, _) => String::from(line_begin),
+                    (Some(n), NumberAlign::Left) => format!("{:<label_width$}", format!("{n}.")),
+                    (Some(n), NumberAlign::Right) => format!("{:>label_width$}", format!("{n}.")),
+                };
+                (
+                    format!("{indent}{line_label} {source}{text}{folded}"),
+                    indent.len() + text_width(&line_label) + 1,
+                )
             }
-            InsertAction::Char(c) => buf.push(c),
-            InsertAction::DeleteChar => _ = buf.pop(),
         }
     }
 
-    fn start_insert_mode(&mut self, direction: KeyCode) {
-        self.mode = Mode::Insert(InsertMode::New);
+    /// Number shown for the first item, 0 or 1
+    fn number_base(&self) -> usize {
+        usize::from(!self.config.number_from_zero)
+    }
 
-        let (list, idx) = match self.curr_tab {
-            Tab::Todos => (&mut self.todos, &mut self.todos_idx),
-            Tab::Dones => (&mut self.dones, &mut self.dones_idx),
+    /// Name of the file `entry` was loaded from, drawn in front of it while
+    /// several are open together
+    fn source_label(&self, entry: &str) -> String {
+        let label = match entry_source(entry) {
+            _ if self.extra_files.is_empty() => return String::new(),
+            0 => self
+                .file_path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy(),
+            n => Cow::Borrowed(self.extra_files[n - 1].label.as_str()),
         };
+        format!("[{label}] ")
+    }
 
-        let insert_idx = match direction {
-            KeyCode::Up => *idx,
-            KeyCode::Down => *idx + 1,
-            _ => unreachable!(),
+    /// Text of an item as drawn, the one search looks through
+    fn search_text<'a>(&self, entry: &'a String) -> &'a str {
+        let (text, _) = self.shown_date(entry);
+        entry_text(text)
+    }
+
+    /// Cursor of the column showing `items`
+    fn selected_in(&self, items: &[String]) -> usize {
+        [Tab::Todos, Tab::Dones, Tab::Someday]
+            .into_iter()
+            .map(|tab| self.column(tab))
+            .find(|(column, ..)| ptr::eq(*column, items))
+            .map_or(0, |(.., idx)| idx)
+    }
+
+    /// Text of an item as drawn, and its completion or else due date shown
+    /// apart. The item being typed keeps its dates inline so the cursor lines
+    /// up with the text
+    fn shown_date<'a>(&self, entry: &'a String) -> (&'a str, Option<&'a str>) {
+        if self.is_editing(entry) {
+            return (entry, None);
         }
-        .clamp(0, list.len());
+        let (text, _) = split_added(entry);
+        (text, split_done(entry).1.or(split_due(entry).1))
+    }
 
-        list.insert(insert_idx, String::new());
-        *idx = insert_idx;
+    /// What's drawn at the right of an item: its date from `shown_date`, or
+    /// else how long ago it was added when `track_age` is on
+    fn date_label<'a>(&self, entry: &'a String) -> Option<Cow<'a, str>> {
+        if let (_, Some(date)) = self.shown_date(entry) {
+            return Some(Cow::Borrowed(date));
+        }
+        if !self.config.track_age || self.is_editing(entry) {
+            return None;
+        }
+        let days = days_between(split_added(entry).1?, &today())?;
+        Some(Cow::Owned(if days > 0 {
+            format!("{days}d ago")
+        } else {
+            "today".to_string()
+        }))
     }
 
-    fn start_edit_mode(&mut self) {
-        let Some(snap) = self.get_current_buffer().cloned() else {
-            return;
-        };
-        self.mode = Mode::Insert(InsertMode::Edit(snap))
+    /// Whether `entry` is the item being typed into
+    fn is_editing(&self, entry: &String) -> bool {
+        matches!(self.mode, Mode::Insert(_))
+            && self
+                .get_current_buffer()
+                .is_some_and(|current| ptr::eq(current, entry))
     }
 
-    fn disable_insert_mode(&mut self) {
-        self.mode = Mode::Normal;
+    fn static_text(&self, term_size: (u16, u16)) -> Ref<'_, StaticText> {
+        if self.static_text.borrow().size != term_size {
+            *self.static_text.borrow_mut() = StaticText::new(term_size);
+        }
+        self.static_text.borrow()
     }
-}
 
-/// Utilities / Internal Helpers
-impl App {
-    fn get_current_buffer(&self) -> Option<&String> {
-        match self.curr_tab {
-            Tab::Todos => self.todos.get(self.todos_idx),
-            Tab::Dones => self.dones.get(self.dones_idx),
+    /// Items shown in the column of `tab`, its indicator and the selected index
+    fn column(&self, tab: Tab) -> (&[String], &str, usize) {
+        let archived = matches!(self.mode, Mode::Archived);
+        let Theme {
+            todo_indicator,
+            done_indicator,
+            ..
+        } = &self.config.theme;
+        match (tab, archived) {
+            (Tab::Todos, false) => (&self.todos, todo_indicator, self.todos_idx),
+            (Tab::Dones, false) => (&self.dones, done_indicator, self.dones_idx),
+            (Tab::Todos, true) => (&self.archived_todos, todo_indicator, self.archived_idx),
+            (Tab::Dones, true) => (&self.archived_dones, done_indicator, self.archived_idx),
+            (Tab::Someday, _) => (&self.someday, globals::SOMEDAY_INDICATOR, self.someday_idx),
         }
     }
 
-    fn goto_list_pos(&mut self, pos: usize) {
-        let idx = match self.curr_tab {
-            Tab::Todos => &mut self.todos_idx,
-            Tab::Dones => &mut self.dones_idx,
+    /// Number of screen rows the item at `idx` occupies once wrapped
+    pub(crate) fn item_height(
+        &self,
+        items: &[String],
+        idx: usize,
+        line_begin: &str,
+        max_width: usize,
+    ) -> usize {
+        // Filtered out and collapsed items aren't drawn at all
+        if self.is_hidden(&items[idx]) || self.is_folded(items, idx) {
+            return 0;
+        }
+        self.wrap_item(items, idx, line_begin, max_width).len()
+    }
+
+    /// Screen rows the items of `items` within `range` occupy in a column
+    /// `max_width` wide. The whole list is needed since numbering affects wrapping.
+    ///
+    /// This is the layout rendering uses, so cursor and scroll math must go
+    /// through it rather than assume one row per item
+    pub(crate) fn rendered_height(
+        &self,
+        items: &[String],
+        range: Range<usize>,
+        line_begin: &str,
+        max_width: usize,
+    ) -> usize {
+        range
+            .map(|idx| self.item_height(items, idx, line_begin, max_width))
+            .sum()
+    }
+
+    /// Screen row of the selected item's first line, skipping wrapped lines above it
+    fn cursor_row(&self, cols: u16) -> u16 {
+        let (items, line_begin, idx) = self.column(self.curr_tab);
+        let (_, max_width) = self.column_geometry(self.curr_tab, cols);
+        let scroll = self.scroll(self.curr_tab);
+        let rows_above =
+            self.rendered_height(items, scroll..idx.min(items.len()), line_begin, max_width);
+
+        1 + rows_above as u16
+    }
+
+    /// Screen position of the cursor: the start of the selected item, or the
+    /// edit offset inside it while typing
+    fn cursor_position(&self, cols: u16) -> (u16, u16) {
+        let (col_offset, max_width) = self.column_geometry(self.curr_tab, cols);
+        let row = self.cursor_row(cols);
+        let (items, line_begin, idx) = self.column(self.curr_tab);
+        let Some(entry) = items.get(idx) else {
+            return (col_offset, row);
         };
+        if !matches!(self.mode, Mode::Insert(_)) {
+            return (col_offset, row);
+        }
+
+        // Grapheme clusters of the full line before the cursor, skipping any
+        // heading or subtask mark
+        let (full_line, offset) = self.full_line(items, idx, line_begin);
+        let text = entry_text(entry);
+        let count = |s: &str| s.graphemes(true).count();
+        let mark = count(entry) - count(text);
+        let label = count(&full_line) - count(text);
+        let mut pos = label + self.edit_cursor.saturating_sub(mark);
+
+        // Wrapped lines split the full line in order, behind their indent
+        let lines = self.wrap_item(items, idx, line_begin, max_width);
+        let last = lines.len() - 1;
+        for (i, line) in lines.iter().enumerate() {
+            let indent = if i == 0 { 0 } else { offset };
+            let chunk = &line[indent..];
+            let glyphs = count(chunk);
+            if pos < glyphs || i == last {
+                let col = indent + text_width(&chunk[..grapheme_to_byte(chunk, pos)]);
+                return (col_offset + col as u16, row + i as u16);
+            }
+            pos -= glyphs;
+        }
+        unreachable!("An item always wraps to at least one line")
+    }
+
+    /// Item of `tab` drawn on screen row `row`, and whether it's the item's first line
+    fn item_at_row(&self, tab: Tab, row: u16, term_size: (u16, u16)) -> Option<(usize, bool)> {
+        let (cols, rows) = term_size;
+        // Items cut off at the bottom don't reach into the hints or status line
+        if row >= rows.saturating_sub(self.bottom_rows()) {
+            return None;
+        }
+        let (items, line_begin, _) = self.column(tab);
+        let (_, max_width) = self.column_geometry(tab, cols);
+        let mut current_line = 1;
+        for idx in self.scroll(tab)..items.len() {
+            let height = self.item_height(items, idx, line_begin, max_width) as u16;
+            if row < current_line + height {
+                return (row >= current_line).then_some((idx, row == current_line));
+            }
+            current_line += height;
+        }
+        None
+    }
 
-        // No need for bound checking due to clamping
+    /// Puts the cursor on item `pos`, or the last one when past the end, and
+    /// scrolls it into view right away rather than on the next draw
+    fn goto_list_pos(&mut self, pos: usize) {
+        let (_, idx) = self.current_list_mut();
         *idx = pos;
+        self.clamp_indexes();
+        // Until the first draw the terminal size isn't known, drawing scrolls then
+        if self.term_size.1 > 0 {
+            self.scroll_into_view(self.term_size);
+        }
+    }
+
+    /// First item drawn in the column of `tab`. The archived lists share one
+    /// offset, used by whichever of them is active
+    fn scroll(&self, tab: Tab) -> usize {
+        let archived = matches!(self.mode, Mode::Archived);
+        match (tab, archived) {
+            (Tab::Todos, false) => self.todos_scroll,
+            (Tab::Dones, false) => self.dones_scroll,
+            (Tab::Someday, _) => self.someday_scroll,
+            _ if tab == self.curr_tab => self.archived_scroll,
+            _ => 0,
+        }
+    }
+
+    fn scroll_mut(&mut self, tab: Tab) -> &mut usize {
+        let archived = matches!(self.mode, Mode::Archived);
+        match (tab, archived) {
+            (Tab::Todos, false) => &mut self.todos_scroll,
+            (Tab::Dones, false) => &mut self.dones_scroll,
+            (Tab::Someday, _) => &mut self.someday_scroll,
+            _ => &mut self.archived_scroll,
+        }
+    }
+
+    /// Scrolls the active column just enough for the selected item to be
+    /// fully drawn between the header and the key hints or status line
+    fn scroll_into_view(&mut self, term_size: (u16, u16)) {
+        let (cols, rows) = term_size;
+        let height = rows.saturating_sub(1 + self.bottom_rows()) as usize;
+        let tab = self.curr_tab;
+        let (items, line_begin, selected) = self.column(tab);
+        let (_, max_width) = self.column_geometry(tab, cols);
+
+        if items.is_empty() {
+            *self.scroll_mut(tab) = 0;
+            return;
+        }
+
+        // Walk up from the selected item for as long as the rows last, so
+        // only the items that end up on screen get wrapped, however far the
+        // cursor jumped
+        let scroll = self.scroll(tab).min(selected);
+        let mut first = selected;
+        let mut used = 0;
+        for idx in (scroll..=selected).rev() {
+            used += self.item_height(items, idx, line_begin, max_width);
+            if used > height {
+                break;
+            }
+            first = idx;
+        }
+        *self.scroll_mut(tab) = first;
     }
 
     fn clamp_indexes(&mut self) {
         self.todos_idx = self.todos_idx.clamp(0, self.todos.len().saturating_sub(1));
         self.dones_idx = self.dones_idx.clamp(0, self.dones.len().saturating_sub(1));
+        self.someday_idx = self.someday_idx.min(self.someday.len().saturating_sub(1));
+        // The cursor never rests on an item the tag filter or a collapsed
+        // item hides, unless every item is
+        for tab in [Tab::Todos, Tab::Dones, Tab::Someday] {
+            let (items, _, idx) = self.column(tab);
+            let shown = |i: &usize| !self.is_hidden(&items[*i]) && !self.is_folded(items, *i);
+            if idx >= items.len() || shown(&idx) {
+                continue;
+            }
+            let next = (idx..items.len()).find(shown);
+            if let Some(i) = next.or_else(|| (0..idx).rev().find(shown)) {
+                match tab {
+                    Tab::Todos => self.todos_idx = i,
+                    Tab::Dones => self.dones_idx = i,
+                    Tab::Someday => self.someday_idx = i,
+                }
+            }
+        }
+
+        let archive = match self.curr_tab {
+            Tab::Todos | Tab::Someday => &self.archived_todos,
+            Tab::Dones => &self.archived_dones,
+        };
+        self.archived_idx = self.archived_idx.min(archive.len().saturating_sub(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A session over `todos` and `dones` with the default config, never
+    /// touching the file it's named after
+    fn app(todos: &[&str], dones: &[&str]) -> App {
+        let list = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+        let loaded = TodoFile {
+            todos: list(todos),
+            dones: list(dones),
+            ..TodoFile::default()
+        };
+        App::with_lists(PathBuf::from("todo.txt"), loaded, Config::default())
+    }
+
+    /// The action `c` is bound to by default
+    fn press(c: char) -> Action {
+        Action::try_from(KeyEvent::from(KeyCode::Char(c))).unwrap()
+    }
+
+    /// Runs `actions` the way the main loop does, clamping the cursors after each
+    fn act(app: &mut App, actions: &[Action]) {
+        for &action in actions {
+            app.execute_action(action).unwrap();
+            app.clamp_indexes();
+        }
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            app.handle_insert_mode(InsertAction::Char(c));
+        }
+    }
+
+    /// A session over `n` todos, every seventh long enough to wrap, on an
+    /// 80x24 screen
+    fn long_list(n: usize) -> App {
+        let items: Vec<_> = (0..n)
+            .map(|i| match i % 7 {
+                0 => format!("item {i} {}", "wraps over several rows ".repeat(6)),
+                _ => format!("item {i}"),
+            })
+            .collect();
+        let items: Vec<_> = items.iter().map(String::as_str).collect();
+        let mut app = app(&items, &[]);
+        app.term_size = (80, 24);
+        app
+    }
+
+    /// Whether every row of the selected item is drawn between the header and
+    /// the key hints, with the list scrolled no further than needed for that
+    fn selected_in_view(app: &App) -> bool {
+        let (cols, rows) = app.term_size;
+        let tab = app.curr_tab;
+        let (items, line_begin, idx) = app.column(tab);
+        let (_, max_width) = app.column_geometry(tab, cols);
+        let scroll = app.scroll(tab);
+        let height = (rows - 1 - app.bottom_rows()) as usize;
+
+        let fits = app.rendered_height(items, scroll..idx + 1, line_begin, max_width) <= height;
+        let tight = scroll == 0
+            || app.rendered_height(items, scroll - 1..idx + 1, line_begin, max_width) > height;
+        scroll <= idx && fits && tight
+    }
+
+    #[test]
+    fn insert_edit_and_cancel() {
+        let mut app = app(&[], &[]);
+        for text in ["one", "two", "three"] {
+            act(&mut app, &[Action::Insert(KeyCode::Down)]);
+            type_text(&mut app, text);
+            app.handle_insert_mode(InsertAction::Enter);
+        }
+        assert_eq!(app.todos, ["one", "two", "three"]);
+
+        act(&mut app, &[Action::MoveCursor(KeyCode::Up), Action::Edit]);
+        type_text(&mut app, " changed");
+        assert_eq!(app.todos[1], "two changed");
+        app.handle_insert_mode(InsertAction::Cancel);
+
+        assert_eq!(app.todos, ["one", "two", "three"]);
+        assert_eq!(app.todos_idx, 1);
+        assert!(matches!(app.mode, Mode::Normal));
+    }
+
+    #[test]
+    fn cancelled_insert_leaves_no_item() {
+        let mut app = app(&["one"], &[]);
+        act(&mut app, &[Action::Insert(KeyCode::Up)]);
+        type_text(&mut app, "zero");
+        app.handle_insert_mode(InsertAction::Cancel);
+
+        assert_eq!(app.todos, ["one"]);
+        assert_eq!(app.todos_idx, 0);
+    }
+
+    #[test]
+    fn completing_moves_item_to_dones() {
+        let mut app = app(&["one", "two"], &[]);
+        act(&mut app, &[Action::EnterStay]);
+
+        assert_eq!(app.todos, ["two"]);
+        assert_eq!(app.dones.len(), 1);
+        assert_eq!(split_done(&app.dones[0]), ("one", Some(today().as_str())));
+        assert!(matches!(app.curr_tab, Tab::Todos));
+    }
+
+    #[test]
+    fn undo_restores_lists() {
+        let mut app = app(&["one", "two"], &[]);
+        act(&mut app, &[Action::MoveItem(KeyCode::Down), Action::Delete]);
+        assert_eq!(app.todos, ["two"]);
+
+        act(&mut app, &[Action::Undo, Action::Undo]);
+        assert_eq!(app.todos, ["one", "two"]);
+    }
+
+    #[test]
+    fn paste_register_drops_entry_marks() {
+        let mut app = app(&["task", "\u{1f}sub", "word"], &[]);
+        act(&mut app, &[Action::MoveCursor(KeyCode::Down), Action::Yank]);
+        act(&mut app, &[Action::MoveCursor(KeyCode::Down), Action::Edit]);
+        app.handle_insert_mode(InsertAction::Left);
+        app.handle_insert_mode(InsertAction::Left);
+        app.handle_insert_mode(InsertAction::PasteRegister);
+        app.handle_insert_mode(InsertAction::Enter);
+
+        assert_eq!(app.todos[2], "wosubrd");
+    }
+
+    /// Selects from the cursor down over `extra` more items and completes them
+    fn complete_selection(app: &mut App, extra: usize) {
+        act(app, &[Action::Visual]);
+        for _ in 0..extra {
+            app.handle_visual_mode(Action::MoveCursor(KeyCode::Down));
+        }
+        app.handle_visual_mode(Action::EnterStay);
+    }
+
+    #[test]
+    fn visual_complete_moves_only_selection() {
+        let head = format!("{HEADING_MARK}head");
+        let mut app = app(&["a", "\u{1f}a1", &head, "b", "c", "d"], &[]);
+        app.config.complete_subtasks = true;
+        complete_selection(&mut app, 3);
+
+        assert_eq!(app.todos, [head.as_str(), "c", "d"]);
+        let done: Vec<_> = app
+            .dones
+            .iter()
+            .map(|e| split_done(entry_text(e)).0)
+            .collect();
+        assert_eq!(done, ["a", "a1", "b"]);
+    }
+
+    #[test]
+    fn visual_complete_with_complete_removes() {
+        let mut app = app(&["a", "b", "c"], &[]);
+        app.config.complete_removes = true;
+        complete_selection(&mut app, 1);
+
+        assert_eq!(app.todos, ["c"]);
+        assert!(app.dones.is_empty());
+    }
+
+    #[test]
+    fn sort_ignores_source_marks() {
+        let merged = with_source("b", 1);
+        let mut app = app(&[&merged, "C", "a"], &[]);
+        act(&mut app, &[Action::Sort]);
+        let texts: Vec<_> = app.todos.iter().map(|e| entry_text(e)).collect();
+        assert_eq!(texts, ["a", "b", "C"]);
+
+        act(&mut app, &[Action::Sort]);
+        let texts: Vec<_> = app.todos.iter().map(|e| entry_text(e)).collect();
+        assert_eq!(texts, ["C", "b", "a"]);
+    }
+
+    #[test]
+    fn jumps_to_items_due_or_of_top_priority() {
+        let mut plain = app(&["plain"], &[]);
+        act(&mut plain, &[Action::JumpPriority]);
+        assert_eq!(plain.status_msg.as_deref(), Some("No item of priority (A)"));
+
+        let due = |date: &str| with_due("due", Some(date));
+        let (late, soon) = (due("2000-01-01"), due("9999-12-31"));
+        let mut app = app(&["(A) first", &soon, "(B) b", &late, "(A) a", "plain"], &[]);
+
+        act(&mut app, &[press('@')]);
+        assert_eq!(app.todos_idx, 3);
+        act(&mut app, &[Action::JumpDue]);
+        assert_eq!(app.todos_idx, 3);
+
+        act(&mut app, &[press('^')]);
+        assert_eq!(app.todos_idx, 4);
+        act(&mut app, &[Action::JumpPriority]);
+        assert_eq!(app.todos_idx, 0);
+    }
+
+    #[test]
+    fn sort_modes_are_restored_per_file() {
+        let file = Path::new("/lists/todo.txt");
+        let mut sorted = app(&["b", "c", "a"], &["y", "x"]);
+        act(&mut sorted, &[Action::Sort]);
+        let mut ui_state = UiState::default();
+        sorted.store_sort_modes(&mut ui_state, file);
+        ui_state.set(&App::sort_key(Tab::Dones, file), "shuffled");
+
+        let mut other = app(&["c", "b", "a"], &[]);
+        other.restore_sort_modes(&ui_state, Path::new("/lists/other.txt"));
+        assert_eq!(other.todos, ["c", "b", "a"]);
+        assert!(!other.dirty);
+
+        let mut reopened = app(&["c", "b", "a"], &["y", "x"]);
+        reopened.restore_sort_modes(&ui_state, file);
+        assert_eq!(reopened.todos, ["a", "b", "c"]);
+        assert_eq!(reopened.dones, ["y", "x"]);
+        assert!(reopened.dirty);
+
+        // Moving an item by hand drops the sorting
+        act(&mut reopened, &[Action::MoveItem(KeyCode::Down)]);
+        reopened.store_sort_modes(&mut ui_state, file);
+        assert_eq!(
+            ui_state.get::<String>(&App::sort_key(Tab::Todos, file)),
+            None
+        );
+    }
+
+    #[test]
+    fn dones_newest_first_keeps_completion_order() {
+        let done = |text: &str, date: &str| with_done(text, Some(date));
+        let (old, new, later) = (
+            done("old", "2000-01-01"),
+            done("new", "2001-01-01"),
+            done("later", "9999-12-31"),
+        );
+        let mut app = app(&["task"], &[&old, "undated", &new, &later]);
+        app.config.dones_newest_first = true;
+        app.restore_sort_modes(&UiState::default(), Path::new("todo.txt"));
+        act(&mut app, &[Action::EnterStay]);
+
+        let texts: Vec<_> = app.dones.iter().map(|e| split_done(e).0).collect();
+        assert_eq!(texts, ["later", "task", "new", "old", "undated"]);
+    }
+
+    #[test]
+    fn due_filter_shows_todos_due_by_today() {
+        let due = |date: &str| with_due("due", Some(date));
+        let (late, now, soon) = (due("2000-01-01"), due(&today()), due("9999-12-31"));
+        let mut app = app(&["plain", &late, &soon, &now], &["done"]);
+        act(&mut app, &[press('T')]);
+
+        let shown: Vec<_> = app.todos.iter().filter(|e| !app.is_hidden(e)).collect();
+        assert_eq!(shown, [&late, &now]);
+        assert!(!app.is_hidden(&app.dones[0]));
+
+        act(&mut app, &[Action::GotoBegin]);
+        assert_eq!(app.todos_idx, 1);
+        act(&mut app, &[Action::MoveCursor(KeyCode::Down)]);
+        assert_eq!(app.todos_idx, 3);
+
+        act(&mut app, &[Action::FilterDue]);
+        assert!(app.todos.iter().all(|e| !app.is_hidden(e)));
+    }
+
+    /// Rows each of the todos takes in a column `width` wide
+    fn heights(app: &App, width: usize) -> Vec<usize> {
+        let (items, line_begin, _) = app.column(Tab::Todos);
+        (0..items.len())
+            .map(|i| app.rendered_height(items, i..i + 1, line_begin, width))
+            .collect()
+    }
+
+    #[test]
+    fn rendered_height_of_wrapped_and_truncated_items() {
+        let mut app = app(&["short", "one two three four five six"], &[]);
+        assert_eq!(heights(&app, 20), [1, 2]);
+        assert_eq!(heights(&app, 40), [1, 1]);
+        let (items, line_begin, _) = app.column(Tab::Todos);
+        assert_eq!(app.rendered_height(items, 0..2, line_begin, 20), 3);
+
+        app.truncate = true;
+        assert_eq!(heights(&app, 20), [1, 1]);
+    }
+
+    #[test]
+    fn rendered_height_of_folded_items() {
+        let mut app = app(&["item", "\u{1f}first", "\u{1f}second", "next"], &[]);
+        assert_eq!(heights(&app, 20), [1, 1, 1, 1]);
+
+        app.collapsed.insert("item".to_string());
+        assert_eq!(heights(&app, 20), [1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn rendered_height_leaves_room_for_dates() {
+        let dated = with_due("one two three four five", Some("2030-01-01"));
+        let app = app(&["one two three four five", &dated], &[]);
+        // The date takes 11 columns at the right of the first row
+        assert_eq!(heights(&app, 30), [1, 3]);
+        assert_eq!(heights(&app, 41), [1, 1]);
+    }
+
+    #[test]
+    fn tabs_keep_their_own_scroll_and_cursor() {
+        let items: Vec<_> = (0..100).map(|i| format!("item {i}")).collect();
+        let items: Vec<_> = items.iter().map(String::as_str).collect();
+        let mut app = app(&items, &items);
+        app.term_size = (80, 24);
+
+        act(&mut app, &[Action::GotoEnd]);
+        app.scroll_into_view(app.term_size);
+        act(&mut app, &[Action::SwitchTab(TabAction::Toggle)]);
+        app.goto_list_pos(50);
+        let before = (
+            app.todos_idx,
+            app.todos_scroll,
+            app.dones_idx,
+            app.dones_scroll,
+        );
+        assert!(app.todos_scroll > 0 && app.dones_scroll > 0);
+        assert_ne!(app.todos_scroll, app.dones_scroll);
+
+        for _ in 0..3 {
+            act(&mut app, &[Action::SwitchTab(TabAction::Toggle)]);
+            app.scroll_into_view(app.term_size);
+        }
+        assert!(matches!(app.curr_tab, Tab::Todos));
+        let after = (
+            app.todos_idx,
+            app.todos_scroll,
+            app.dones_idx,
+            app.dones_scroll,
+        );
+        assert_eq!(after, before);
+    }
+
+    #[test]
+    fn cursor_sits_on_first_row_of_selected_item() {
+        let long = "wraps over several rows ".repeat(6);
+        let mut app = app(&[&long, "b", &long], &[]);
+        let (_, width) = app.column_geometry(Tab::Todos, 80);
+        let rows = heights(&app, width);
+        assert!(rows[0] > 1);
+
+        act(&mut app, &[Action::MoveCursor(KeyCode::Down)]);
+        assert_eq!(app.cursor_position(80), (0, 1 + rows[0] as u16));
+        act(&mut app, &[Action::MoveCursor(KeyCode::Down)]);
+        assert_eq!(app.cursor_row(80), 1 + rows[0] as u16 + 1);
+        // Continuation rows aren't stops of their own
+        act(&mut app, &[Action::MoveCursor(KeyCode::Up)]);
+        assert_eq!(app.todos_idx, 1);
+    }
+
+    #[test]
+    fn insert_position_on_empty_and_short_lists() {
+        assert_eq!(App::insert_position(0, 0, KeyCode::Up), 0);
+        assert_eq!(App::insert_position(0, 5, KeyCode::Down), 0);
+        assert_eq!(App::insert_position(3, 1, KeyCode::Up), 1);
+        assert_eq!(App::insert_position(3, 1, KeyCode::Down), 2);
+        // A cursor left past the end inserts around the last item
+        assert_eq!(App::insert_position(3, 9, KeyCode::Up), 2);
+        assert_eq!(App::insert_position(3, 9, KeyCode::Down), 3);
+
+        let mut app = app(&[], &[]);
+        act(&mut app, &[Action::Insert(KeyCode::Up)]);
+        type_text(&mut app, "first");
+        app.handle_insert_mode(InsertAction::Enter);
+        assert_eq!(app.todos, ["first"]);
+        assert_eq!(app.todos_idx, 0);
+    }
+
+    #[test]
+    fn highlight_fills_every_row_of_selected_item() {
+        let long = "wraps over several rows ".repeat(6);
+        let mut app = app(&["first", &long, "last"], &[]);
+        app.term_size = (80, 24);
+        act(&mut app, &[Action::MoveCursor(KeyCode::Down)]);
+        let (_, width) = app.column_geometry(Tab::Todos, 80);
+        let rows = heights(&app, width)[1] as u16;
+        assert!(rows > 1);
+
+        let mut frame = Frame::new(app.term_size, false);
+        app.write_todos_dones(&mut frame, app.term_size);
+        let highlighted = |row: u16| (0..width as u16).all(|col| frame.is_highlighted(col, row));
+        assert!((2..2 + rows).all(highlighted));
+        assert!(!frame.is_highlighted(0, 1));
+        assert!(!frame.is_highlighted(0, 2 + rows));
+    }
+
+    #[test]
+    fn wide_and_combining_indicators_keep_columns_aligned() {
+        let wide = "日本".repeat(30);
+        let mut app = app(&[&wide], &["done"]);
+        app.term_size = (80, 24);
+        app.config.theme.todo_indicator = "✅".into();
+        app.config.theme.done_indicator = "e\u{301}".into();
+        let (done_col, _) = app.column_geometry(Tab::Dones, 80);
+        let (_, todo_width) = app.column_geometry(Tab::Todos, 80);
+
+        let mut frame = Frame::new(app.term_size, false);
+        app.write_header(&mut frame, app.term_size);
+        app.write_todos_dones(&mut frame, app.term_size);
+
+        // The right header ends on the last column
+        assert_eq!(frame.glyph(79, 0), Some(")"));
+        assert_eq!(frame.glyph(0, 1), Some("✅"));
+        assert_eq!(frame.glyph(1, 1), None);
+        assert_eq!(frame.glyph(3, 1), Some("日"));
+        assert_eq!(frame.glyph(done_col, 1), Some("e\u{301}"));
+        assert_eq!(frame.glyph(done_col + 2, 1), Some("d"));
+        // Continuation lines line up under the text
+        assert_eq!(frame.glyph(3, 2), Some("日"));
+        // Wide glyphs wrap rather than spill into the gap before the dones
+        let rows = heights(&app, todo_width)[0] as u16;
+        assert!(rows > 1);
+        assert!((1..1 + rows).all(|row| frame.glyph(todo_width as u16, row) == Some(" ")));
+    }
+
+    #[test]
+    fn delete_with_count_keeps_items_for_paste() {
+        let mut app = app(&["a", "b", "c", "d", "e"], &[]);
+        act(&mut app, &[Action::MoveCursor(KeyCode::Down)]);
+        app.pending_count = Some(3);
+        act(&mut app, &[Action::Delete]);
+        assert_eq!(app.todos, ["a", "e"]);
+        assert_eq!(app.registers[&globals::UNNAMED_REGISTER], ["b", "c", "d"]);
+
+        // A count past the end deletes what's left
+        app.pending_count = Some(10);
+        act(&mut app, &[Action::Delete]);
+        assert_eq!(app.todos, ["a"]);
+        act(&mut app, &[Action::Paste(KeyCode::Down)]);
+        assert_eq!(app.todos, ["a", "e"]);
+    }
+
+    #[test]
+    fn backspace_deletes_whole_grapheme() {
+        let mut app = app(&["cafe\u{301} \u{1f44d}"], &[]);
+        act(&mut app, &[Action::Edit]);
+        app.handle_insert_mode(InsertAction::DeleteChar);
+        assert_eq!(app.todos[0], "cafe\u{301} ");
+        app.handle_insert_mode(InsertAction::DeleteChar);
+        app.handle_insert_mode(InsertAction::DeleteChar);
+        assert_eq!(app.todos[0], "caf");
+    }
+
+    #[test]
+    fn cursor_stays_put_after_delete() {
+        let mut app = app(&["a", "b", "c"], &[]);
+        act(
+            &mut app,
+            &[Action::MoveCursor(KeyCode::Down), Action::Delete],
+        );
+        assert_eq!(app.todos, ["a", "c"]);
+        assert_eq!(app.todos_idx, 1);
+
+        // Deleting the last item moves up to the new last one
+        act(&mut app, &[Action::Delete]);
+        assert_eq!(app.todos, ["a"]);
+        assert_eq!(app.todos_idx, 0);
+        act(&mut app, &[Action::Delete]);
+        assert!(app.todos.is_empty());
+        assert_eq!(app.todos_idx, 0);
+    }
+
+    #[test]
+    fn word_motions_and_delete_in_insert_mode() {
+        let mut app = app(&["buy fresh milk"], &[]);
+        act(&mut app, &[Action::Edit]);
+        app.handle_insert_mode(InsertAction::WordLeft);
+        assert_eq!(app.edit_cursor, 10);
+        app.handle_insert_mode(InsertAction::DeleteWord);
+        assert_eq!(app.todos[0], "buy milk");
+        assert_eq!(app.edit_cursor, 4);
+
+        app.handle_insert_mode(InsertAction::Home);
+        app.handle_insert_mode(InsertAction::WordRight);
+        assert_eq!(app.edit_cursor, 3);
+        app.handle_insert_mode(InsertAction::DeleteWord);
+        app.handle_insert_mode(InsertAction::DeleteWord);
+        assert_eq!(app.todos[0], " milk");
+        assert_eq!(app.edit_cursor, 0);
+    }
+
+    #[test]
+    fn goto_end_keeps_selected_item_in_view() {
+        let mut app = long_list(5000);
+        act(&mut app, &[Action::GotoEnd]);
+        assert_eq!(app.todos_idx, 4999);
+        assert!(selected_in_view(&app));
+
+        act(&mut app, &[Action::GotoBegin]);
+        assert_eq!(app.todos_scroll, 0);
+        act(&mut app, &[Action::FullPage(KeyCode::Down)]);
+        app.scroll_into_view(app.term_size);
+        assert!(selected_in_view(&app));
+    }
+
+    #[test]
+    fn narrow_terminal_shows_one_column() {
+        let mut app = app(&["buy milk"], &["call mom"]);
+        let narrow = MIN_SPLIT_COLS - 1;
+        assert!(app.is_single_column(narrow));
+        assert_eq!(
+            app.column_geometry(Tab::Todos, narrow),
+            (0, narrow as usize - 1)
+        );
+        assert_eq!(
+            app.column_geometry(Tab::Dones, narrow),
+            (0, narrow as usize - 1)
+        );
+        for cols in [0, 1] {
+            assert_eq!(app.column_geometry(Tab::Todos, cols), (0, 0));
+        }
+
+        let half = MIN_SPLIT_COLS / 2;
+        assert!(!app.is_single_column(MIN_SPLIT_COLS));
+        assert_eq!(
+            app.column_geometry(Tab::Todos, MIN_SPLIT_COLS),
+            (0, half as usize - 1)
+        );
+        assert_eq!(
+            app.column_geometry(Tab::Dones, MIN_SPLIT_COLS),
+            (half, half as usize)
+        );
+
+        act(&mut app, &[Action::ToggleSingleColumn]);
+        assert!(app.is_single_column(MIN_SPLIT_COLS));
+    }
+
+    #[test]
+    fn move_item_to_either_end() {
+        let mut app = app(&["a", "b", "c"], &[]);
+        act(&mut app, &[Action::MoveItemToEnd(KeyCode::Down)]);
+        assert_eq!(app.todos, ["b", "c", "a"]);
+        assert_eq!(app.todos_idx, 2);
+
+        act(&mut app, &[Action::MoveItemToEnd(KeyCode::Up)]);
+        assert_eq!(app.todos, ["a", "b", "c"]);
+        assert_eq!(app.todos_idx, 0);
+
+        app.todos.clear();
+        app.todos_idx = 0;
+        act(&mut app, &[Action::MoveItemToEnd(KeyCode::Down)]);
+        assert!(app.todos.is_empty());
+    }
+
+    #[test]
+    fn cursor_wraps_at_list_ends_when_asked() {
+        let mut app = app(&["a", "b", "c"], &["done"]);
+        app.todos_idx = 2;
+        act(&mut app, &[Action::MoveCursor(KeyCode::Down)]);
+        assert_eq!(app.todos_idx, 2);
+        app.todos_idx = 0;
+        act(&mut app, &[Action::MoveCursor(KeyCode::Up)]);
+        assert_eq!(app.todos_idx, 0);
+
+        app.config.wrap_cursor = true;
+        act(&mut app, &[Action::MoveCursor(KeyCode::Up)]);
+        assert_eq!(app.todos_idx, 2);
+        act(&mut app, &[Action::MoveCursor(KeyCode::Down)]);
+        assert_eq!(app.todos_idx, 0);
+        assert!(matches!(app.curr_tab, Tab::Todos));
+    }
+
+    #[test]
+    fn completed_and_reopened_go_to_the_configured_end() {
+        for end in [ListEnd::Bottom, ListEnd::Top] {
+            let mut app = app(&["a", "b"], &["x", "y"]);
+            app.config.completed_position = end;
+            app.config.reopened_position = end;
+
+            let top = end == ListEnd::Top;
+            act(&mut app, &[Action::EnterStay]);
+            assert!(app.dones[if top { 0 } else { 2 }].starts_with('a'));
+
+            // Reopen "y", wherever it is now
+            act(&mut app, &[Action::SwitchTab(TabAction::Toggle)]);
+            app.dones_idx = if top { 2 } else { 1 };
+            act(&mut app, &[Action::EnterStay]);
+            let expected = if top { ["y", "b"] } else { ["b", "y"] };
+            assert_eq!(app.todos, expected);
+        }
+    }
+
+    #[test]
+    fn control_characters_never_reach_item_text() {
+        let mut app = app(&[], &[]);
+        act(&mut app, &[Action::Insert(KeyCode::Down)]);
+        type_text(&mut app, "a\tb\nc\u{7}\u{1b}d");
+        app.handle_insert_mode(InsertAction::Enter);
+        assert_eq!(app.todos, ["a bcd"]);
+    }
+
+    #[test]
+    fn number_base_alignment_and_width() {
+        // First and last line of a list of `len` items
+        let ends = |len: usize, config: fn(&mut Config)| {
+            let items = vec!["x"; len];
+            let mut list = app(&items, &[]);
+            list.show_number = Numbers::Absolute;
+            config(&mut list.config);
+            let line = |idx| list.full_line(&list.todos, idx, "-").0;
+            (line(0), line(len - 1))
+        };
+
+        let one_based = |_: &mut Config| {};
+        assert_eq!(ends(9, one_based), ("1. x".into(), "9. x".into()));
+        assert_eq!(ends(10, one_based), (" 1. x".into(), "10. x".into()));
+        assert_eq!(ends(100, one_based), ("  1. x".into(), "100. x".into()));
+
+        let zero_based = |config: &mut Config| config.number_from_zero = true;
+        assert_eq!(ends(10, zero_based), ("0. x".into(), "9. x".into()));
+        assert_eq!(ends(100, zero_based), (" 0. x".into(), "99. x".into()));
+
+        let left = |config: &mut Config| config.number_align = NumberAlign::Left;
+        assert_eq!(ends(10, left), ("1.  x".into(), "10. x".into()));
+
+        // A fixed width keeps short lists from shifting as they grow
+        let fixed = |config: &mut Config| config.number_width = Some(3);
+        assert_eq!(ends(9, fixed), ("  1. x".into(), "  9. x".into()));
+        assert_eq!(ends(100, fixed), ("  1. x".into(), "100. x".into()));
+    }
+
+    #[test]
+    fn max_length_measures_text_without_dates() {
+        let subtask = format!("{SUBTASK_MARK}another long one");
+        let mut app = app(
+            &[
+                "short text @due:2026-11-01",
+                "a long item @due:2026-11-01",
+                &subtask,
+            ],
+            &[],
+        );
+        let path = env::temp_dir().join(format!("tick-max-length-{}.txt", process::id()));
+
+        let err = app.write_file(&path, None, Some(10)).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "items longer than 10 characters: todo 2, todo 3"
+        );
+        app.config.number_from_zero = true;
+        let err = app.write_file(&path, None, Some(10)).err().unwrap();
+        assert!(err.to_string().ends_with("todo 1, todo 2"));
+
+        // Only the text is cut, the date and the subtask's indent stay
+        app.config.max_length_truncate = true;
+        app.write_file(&path, None, Some(10)).unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            saved,
+            "TODO: short text @due:2026-11-01\n\
+             TODO: a long it… @due:2026-11-01\n  \
+             TODO: another l…\n"
+        );
+    }
+
+    #[test]
+    fn move_to_other_tab_keeps_subtasks_together() {
+        let sub = |text: &str| format!("{SUBTASK_MARK}{text}");
+        let heading = format!("{HEADING_MARK}Work");
+        let (a1, x1) = (sub("a1"), sub("x1"));
+        let mut app = app(&[&heading, "a", &a1, "b"], &["x", &x1, "y"]);
+        let undated = |list: &[String]| -> Vec<String> {
+            list.iter()
+                .map(|entry| split_done(entry).0.to_string())
+                .collect()
+        };
+
+        act(&mut app, &[Action::MoveToOtherTab]);
+        assert_eq!(app.todos.len(), 4);
+
+        // The item takes its subtasks along, and goes past those of "x"
+        app.todos_idx = 1;
+        act(&mut app, &[Action::MoveToOtherTab]);
+        assert_eq!(app.todos, [&heading, "b"]);
+        assert_eq!(undated(&app.dones), ["x", &x1, "a", &a1, "y"]);
+    }
+
+    #[test]
+    fn archive_and_restore_keep_subtasks_together() {
+        let sub = |text: &str| format!("{SUBTASK_MARK}{text}");
+        let (a1, a2, b1) = (sub("a1"), sub("a2"), sub("b1"));
+        let mut app = app(&["a", &a1, &a2, "b", &b1], &[]);
+
+        act(&mut app, &[Action::Archive]);
+        assert_eq!(app.todos, ["b", &b1]);
+        assert_eq!(app.archived_todos, ["a", &a1, &a2]);
+
+        // A subtask archived alone becomes an item of its own
+        app.todos_idx = 1;
+        act(&mut app, &[Action::Archive]);
+        assert_eq!(app.todos, ["b"]);
+        assert_eq!(app.archived_todos, ["a", &a1, &a2, "b1"]);
+
+        act(&mut app, &[Action::ShowArchived]);
+        app.handle_archived_mode(Action::Archive);
+        assert_eq!(app.todos, ["b", "a", &a1, &a2]);
+        assert_eq!(app.archived_todos, ["b1"]);
+    }
+
+    #[test]
+    fn pasted_lines_are_cleaned_and_keep_the_source() {
+        let first = with_source("a", 1);
+        let mut app = app(&[&first], &[]);
+        act(&mut app, &[Action::Edit]);
+        app.handle_paste_event("b\nc\td\u{7}\n\u{1b}\n").unwrap();
+        assert_eq!(app.todos, [with_source("ab", 1), with_source("c d", 1)]);
+
+        // The clipboard's lines go the same way, marks of their own dropped
+        let lines = "\u{1f}sub\n\u{1e}head\t\n\u{1d}2x\n".lines();
+        assert_eq!(
+            app.pasted_items(lines),
+            [
+                with_source("sub", 1),
+                with_source("head", 1),
+                with_source("2x", 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn status_flags_show_active_toggles() {
+        let mut app = app(&["b", "a"], &[]);
+        assert_eq!(app.status_flags(), "W");
+
+        app.show_number = Numbers::Relative;
+        app.truncate = true;
+        app.filter = Some(Filter::Due(today()));
+        assert_eq!(app.status_flags(), "NF");
+
+        app.filter = None;
+        act(&mut app, &[Action::Sort]);
+        assert_eq!(app.status_flags(), "NS+");
+    }
+
+    #[test]
+    fn visual_delete_can_be_undone() {
+        let mut app = app(&["a", "b", "c"], &[]);
+        act(&mut app, &[Action::Visual]);
+        app.handle_visual_mode(Action::MoveCursor(KeyCode::Down));
+        app.handle_visual_mode(Action::DeleteOperator);
+        assert_eq!(app.todos, ["c"]);
+        assert!(app.dirty);
+
+        act(&mut app, &[Action::Undo]);
+        assert_eq!(app.todos, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn duplicate_warning_compares_subtasks_of_one_item() {
+        let call = format!("{SUBTASK_MARK}call");
+        let mut app = app(&["A", &call, "B", &call, "A "], &[]);
+        app.todos_idx = 3;
+        app.warn_duplicate();
+        assert!(app.status_msg.is_none());
+
+        app.todos_idx = 4;
+        app.warn_duplicate();
+        assert_eq!(app.status_msg.as_deref(), Some("Already in the list: A"));
+    }
+
+    #[test]
+    fn shuttle_someday_keeps_subtasks_together() {
+        let b1 = format!("{SUBTASK_MARK}b1");
+        let a1 = format!("{SUBTASK_MARK}a1");
+        let mut app = app(&["A", &a1, "B", &b1], &[]);
+
+        app.todos_idx = 2;
+        act(&mut app, &[Action::ShuttleSomeday]);
+        assert_eq!(app.todos, ["A", &a1]);
+        assert_eq!(app.someday, ["B", &b1]);
+
+        // A subtask sent alone leaves its item
+        app.todos_idx = 1;
+        act(&mut app, &[Action::ShuttleSomeday]);
+        assert_eq!(app.todos, ["A"]);
+        assert_eq!(app.someday, ["B", &b1, "a1"]);
+    }
+
+    #[test]
+    fn goto_begin_and_end_scroll_without_a_redraw() {
+        let mut app = long_list(200);
+        app.todos_idx = 100;
+        app.scroll_into_view(app.term_size);
+        let mid_scroll = app.todos_scroll;
+        assert!(mid_scroll > 0);
+
+        act(&mut app, &[Action::GotoEnd]);
+        assert_eq!(app.todos_idx, 199);
+        assert!(app.todos_scroll > mid_scroll);
+        assert!(selected_in_view(&app));
+
+        act(&mut app, &[Action::GotoBegin]);
+        assert_eq!((app.todos_idx, app.todos_scroll), (0, 0));
     }
 }
@@ -0,0 +1,396 @@
+use std::collections::HashSet;
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use dirs::home_dir;
+use tick::item::{Format, Prefixes};
+
+use crate::config::Config;
+use crate::helpers::{ExportFormat, StatusFormat};
+
+pub enum Command {
+    Run {
+        file_path: PathBuf,
+        /// Opened alongside `file_path`, their items merged into its lists
+        extra_paths: Vec<PathBuf>,
+        config: Box<Config>,
+        start_at: Option<StartAt>,
+    },
+    Doctor {
+        file_path: PathBuf,
+    },
+    /// Prints the lists in another format without touching the file
+    Export {
+        file_path: PathBuf,
+        format: ExportFormat,
+        /// Layout of the file being read
+        file_format: Format,
+        prefixes: Prefixes,
+    },
+    /// Prints how many items the lists hold without touching the file
+    Status {
+        file_path: PathBuf,
+        format: StatusFormat,
+        file_format: Format,
+        prefixes: Prefixes,
+    },
+    /// Appends one item to the file without starting the UI
+    Add {
+        file_path: PathBuf,
+        text: String,
+        /// Add to the dones instead of the todos
+        done: bool,
+        file_format: Format,
+        prefixes: Prefixes,
+        backup: bool,
+    },
+    /// Rewrites the file in canonical form without starting the UI
+    Fmt {
+        file_path: PathBuf,
+        file_format: Format,
+        prefixes: Prefixes,
+        backup: bool,
+    },
+    Version,
+    Keys,
+    Help,
+}
+
+/// Printed by `--help`, followed by the keybindings
+pub const USAGE: &str = "\
+Usage: tick [flags] [path...]
+       tick doctor [path]
+       tick export [--format json|md] [--todo-txt] [path]
+       tick status [--format plain|json] [--todo-txt] [path]
+       tick add [--done] [--todo-txt] <text> [path]
+       tick fmt [--todo-txt] [path]
+
+Flags:
+  -h, --help         Print this help
+  -V, --version      Print the version
+  --keys             Print the keybindings
+  --print-on-exit    Print the lists to the terminal after quitting
+  --read-only        View the file without changing or saving it
+  --backup           Copy the file to <name>.bak before each save
+  --save-log         Log each save with its time to <name>.save.log
+  --swap-columns     Show DONE on the left and TODO on the right
+  --no-alt-screen    Draw on the main screen, keeping the scrollback
+  --todo-txt         Read and write the file in todo.txt format
+  --goto <n>         Start on the n-th todo
+  --find <text>      Start on the first todo, or else done, containing the text
+
+Path:
+  Without a path, $TODO_FILE is used, or else ~/TODO. A directory opens the
+  TODO file inside it, and a file whose first line is `@<path>` opens the file
+  it points to. More paths open their files alongside the first, each item
+  labeled with its file's name and saved back to it.
+
+File format:
+  One item per line, `TODO: ` or `DONE: ` followed by the text. Blank lines,
+  comments and any other line are kept where they are.
+
+Exit status:
+  0 after saving on quit, 1 on errors, 2 when the path is a directory, 3
+  after quitting without saving and 4 when stdout isn't a terminal.
+";
+
+/// The TODO file resolved to a directory, like a `TODO` directory inside the
+/// one given. Exits with its own status, see [`crate::globals::EXIT_PATH_IS_DIR`]
+#[derive(Debug)]
+pub struct PathIsDir(pub PathBuf);
+
+impl fmt::Display for PathIsDir {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} is a directory, not a TODO file", self.0.display())
+    }
+}
+
+impl std::error::Error for PathIsDir {}
+
+/// Item the cursor starts on, given by `--goto` or `--find`
+pub enum StartAt {
+    /// 1-based position in the todo list
+    Position(usize),
+    /// First item containing the text, todos before dones
+    Text(String),
+}
+
+/// Parses the process arguments, applying flags on top of the loaded config
+pub fn parse<I>(args: I) -> Result<Command>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = args.into_iter().peekable();
+    if args.peek().is_some_and(|arg| arg == "doctor") {
+        args.next();
+        let format = Config::load()?.format;
+        let file_path = resolve_path(args.next().map(PathBuf::from), format)?;
+        return Ok(Command::Doctor { file_path });
+    }
+    if args.peek().is_some_and(|arg| arg == "export") {
+        args.next();
+        return parse_export(args);
+    }
+    if args.peek().is_some_and(|arg| arg == "status") {
+        args.next();
+        return parse_status(args);
+    }
+    if args.peek().is_some_and(|arg| arg == "add") {
+        args.next();
+        return parse_add(args);
+    }
+    if args.peek().is_some_and(|arg| arg == "fmt") {
+        args.next();
+        return parse_fmt(args);
+    }
+
+    let mut overrides = Vec::new();
+    let mut paths = Vec::new();
+    let mut start_at = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--help" | "-h" => return Ok(Command::Help),
+            "--version" | "-V" => return Ok(Command::Version),
+            "--keys" => return Ok(Command::Keys),
+            "--print-on-exit" => overrides.push(("print_on_exit", "true")),
+            "--read-only" => overrides.push(("read_only", "true")),
+            "--backup" => overrides.push(("backup", "true")),
+            "--save-log" => overrides.push(("save_log", "true")),
+            "--swap-columns" => overrides.push(("swap_columns", "true")),
+            "--no-alt-screen" => overrides.push(("no_alt_screen", "true")),
+            "--todo-txt" => overrides.push(("format", "todo.txt")),
+            "--goto" => {
+                let pos = args.next().context("--goto expects an item number")?;
+                let pos = pos
+                    .parse()
+                    .with_context(|| format!("--goto expects an item number, got {:?}", pos))?;
+                start_at = Some(StartAt::Position(pos));
+            }
+            "--find" => {
+                let text = args.next().context("--find expects the text to look for")?;
+                start_at = Some(StartAt::Text(text));
+            }
+            flag if flag.starts_with("--") => bail!("Unknown flag: {}", flag),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    let mut config = Config::load()?;
+    for (key, value) in overrides {
+        config.set(key, value)?;
+    }
+
+    let mut paths = paths.into_iter();
+    let file_path = resolve_path(paths.next(), config.format)?;
+    let extra_paths = paths
+        .map(|path| resolve_path(Some(path), config.format))
+        .collect::<Result<_>>()?;
+    Ok(Command::Run {
+        file_path,
+        extra_paths,
+        config: Box::new(config),
+        start_at,
+    })
+}
+
+/// `tick export [--format json|md] [--todo-txt] [path]`, JSON by default
+fn parse_export<I>(args: I) -> Result<Command>
+where
+    I: Iterator<Item = String>,
+{
+    let mut args = args.peekable();
+    let mut format = ExportFormat::Json;
+    let mut config = Config::load()?;
+    let mut path = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                let value = args.next().context("--format expects json or md")?;
+                format = value.parse()?;
+            }
+            "--todo-txt" => config.set("format", "todo.txt")?,
+            flag if flag.starts_with("--") => bail!("Unknown flag: {}", flag),
+            _ if path.is_some() => bail!("Only one file path can be given"),
+            _ => path = Some(PathBuf::from(arg)),
+        }
+    }
+
+    Ok(Command::Export {
+        file_path: resolve_path(path, config.format)?,
+        format,
+        file_format: config.format,
+        prefixes: config.prefixes,
+    })
+}
+
+/// `tick status [--format plain|json] [--todo-txt] [path]`, plain by default
+fn parse_status<I>(args: I) -> Result<Command>
+where
+    I: Iterator<Item = String>,
+{
+    let mut args = args.peekable();
+    let mut format = StatusFormat::Plain;
+    let mut config = Config::load()?;
+    let mut path = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                let value = args.next().context("--format expects plain or json")?;
+                format = value.parse()?;
+            }
+            "--todo-txt" => config.set("format", "todo.txt")?,
+            flag if flag.starts_with("--") => bail!("Unknown flag: {}", flag),
+            _ if path.is_some() => bail!("Only one file path can be given"),
+            _ => path = Some(PathBuf::from(arg)),
+        }
+    }
+
+    Ok(Command::Status {
+        file_path: resolve_path(path, config.format)?,
+        format,
+        file_format: config.format,
+        prefixes: config.prefixes,
+    })
+}
+
+/// `tick add [--done] [--todo-txt] <text> [path]`. Like the other commands,
+/// a file named `add` is opened with `tick ./add`
+fn parse_add<I>(args: I) -> Result<Command>
+where
+    I: Iterator<Item = String>,
+{
+    let mut config = Config::load()?;
+    let mut done = false;
+    let mut text = None;
+    let mut path = None;
+
+    for arg in args {
+        match arg.as_str() {
+            "--done" => done = true,
+            "--todo-txt" => config.set("format", "todo.txt")?,
+            flag if flag.starts_with("--") => bail!("Unknown flag: {}", flag),
+            _ if text.is_none() => text = Some(arg),
+            _ if path.is_some() => bail!("Only one file path can be given"),
+            _ => path = Some(PathBuf::from(arg)),
+        }
+    }
+
+    let text = text
+        .filter(|text| !text.trim().is_empty())
+        .context("add expects the text of the item")?;
+    Ok(Command::Add {
+        file_path: resolve_path(path, config.format)?,
+        text,
+        done,
+        file_format: config.format,
+        prefixes: config.prefixes,
+        backup: config.backup,
+    })
+}
+
+/// `tick fmt [--todo-txt] [path]`
+fn parse_fmt<I>(args: I) -> Result<Command>
+where
+    I: Iterator<Item = String>,
+{
+    let mut config = Config::load()?;
+    let mut path = None;
+
+    for arg in args {
+        match arg.as_str() {
+            "--todo-txt" => config.set("format", "todo.txt")?,
+            flag if flag.starts_with("--") => bail!("Unknown flag: {}", flag),
+            _ if path.is_some() => bail!("Only one file path can be given"),
+            _ => path = Some(PathBuf::from(arg)),
+        }
+    }
+
+    Ok(Command::Fmt {
+        file_path: resolve_path(path, config.format)?,
+        file_format: config.format,
+        prefixes: config.prefixes,
+        backup: config.backup,
+    })
+}
+
+/// Falls back to `$TODO_FILE`, then `~/TODO`, and looks for a `TODO` file
+/// inside directories. Pointer files are only followed in `format` tick
+pub fn resolve_path(path: Option<PathBuf>, format: Format) -> Result<PathBuf> {
+    let from_env = || env::var_os("TODO_FILE").filter(|var| !var.is_empty());
+    let untreated_path = match path.or_else(|| from_env().map(PathBuf::from)) {
+        Some(path) => path,
+        None => home_dir()
+            .context("Unable to find home directory")?
+            .join("TODO"),
+    };
+
+    let file_path = if untreated_path.is_dir() {
+        untreated_path.join("TODO")
+    } else {
+        untreated_path
+    };
+
+    // Caught here, before any command gets to the file or the screen
+    let file_path = match format {
+        Format::Tick => follow_pointers(file_path)?,
+        // There a first line like `@phone call mom` is a task with a context
+        Format::TodoTxt => file_path,
+    };
+    if file_path.is_dir() {
+        return Err(PathIsDir(file_path).into());
+    }
+    Ok(file_path)
+}
+
+/// A file whose first line is `@<path>` points at the real TODO file.
+/// Relative targets are resolved from the pointer file's directory
+fn follow_pointers(mut file_path: PathBuf) -> Result<PathBuf> {
+    let mut visited = HashSet::new();
+
+    loop {
+        let Some(target) = read_pointer(&file_path) else {
+            return Ok(file_path);
+        };
+
+        if !visited.insert(file_path.clone()) {
+            bail!("Pointer loop detected at {}", file_path.display());
+        }
+
+        let base = file_path.parent().unwrap_or(Path::new("."));
+        file_path = base.join(target);
+    }
+}
+
+fn read_pointer(file_path: &Path) -> Option<PathBuf> {
+    let file = File::open(file_path).ok()?;
+    let first_line = BufReader::new(file).lines().next()?.ok()?;
+    let target = first_line.strip_prefix('@')?.trim();
+    (!target.is_empty()).then(|| PathBuf::from(target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pointers_are_only_followed_in_tick_format() {
+        let dir = env::temp_dir().join(format!("tick-pointer-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pointer = dir.join("pointer");
+        std::fs::write(&pointer, "@real\n").unwrap();
+        std::fs::write(dir.join("real"), "TODO: a\n").unwrap();
+
+        let tick = resolve_path(Some(pointer.clone()), Format::Tick);
+        let todo_txt = resolve_path(Some(pointer.clone()), Format::TodoTxt);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(tick.unwrap(), dir.join("real"));
+        assert_eq!(todo_txt.unwrap(), pointer);
+    }
+}
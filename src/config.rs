@@ -0,0 +1,453 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use crossterm::event::KeyCode;
+use crossterm::style::Color;
+use tick::item::{Format, Prefixes};
+
+use crate::action::Action;
+use crate::globals;
+use crate::helpers::{Highlight, parse_color};
+
+/// User settings, read from `<config dir>/tick/config`
+///
+/// The file holds one `key = value` pair per line and `#` starts a comment.
+/// A missing file leaves every setting at its default.
+#[derive(Default)]
+pub struct Config {
+    /// Print the lists to the normal screen after leaving the alternate screen
+    pub print_on_exit: bool,
+    /// Only view the file: nothing can be changed and it's never written
+    pub read_only: bool,
+    /// Copy the file to `<file>.bak` before each save
+    pub backup: bool,
+    /// Append a timestamped line to `<file>.save.log` on each save, instead
+    /// of printing where the file was saved after quitting
+    pub save_log: bool,
+    /// Longest item text the file may hold, in characters and without its
+    /// dates, no limit when `None`
+    pub max_length: Option<usize>,
+    /// Longer items are saved cut off with `…`, instead of failing the save
+    pub max_length_truncate: bool,
+    /// Say so on the status line when an added or edited item is already in
+    /// its list
+    pub warn_duplicates: bool,
+    /// Lines pasted while typing an item are joined into it by spaces,
+    /// instead of each becoming an item of its own
+    pub paste_join: bool,
+    /// Completing a todo deletes it instead of moving it to the dones
+    pub complete_removes: bool,
+    /// Append items removed by `complete_removes` to `<file>.log`
+    pub completion_log: bool,
+    /// Completing or restoring an item puts it at the same index in the other
+    /// list instead of at its end
+    pub complete_in_place: bool,
+    /// End of the dones a completed todo goes to, and of the todos a
+    /// reopened done goes to
+    pub completed_position: ListEnd,
+    pub reopened_position: ListEnd,
+    /// The dones are kept sorted by completion date, latest first, and a
+    /// completed todo goes in at its place among them
+    pub dones_newest_first: bool,
+    /// Completing or restoring an item moves its subtasks along with it,
+    /// instead of leaving them behind as items of their own
+    pub complete_subtasks: bool,
+    /// Draw the dones in the left column and the todos in the right one
+    pub swap_columns: bool,
+    /// Draw on the main screen instead of the alternate one, so what was on
+    /// it stays in the scrollback and the lists stay in view after quitting
+    pub no_alt_screen: bool,
+    /// Capture the mouse so items can be toggled by clicking their checkbox
+    /// and reordered by dragging them
+    pub mouse: bool,
+    /// Checkboxes, colors and emphasis used when drawing
+    pub theme: Theme,
+    /// Draw without colors or emphasis, marking the selected item with `>`.
+    /// On by default when `NO_COLOR` is set or `TERM` is `dumb`
+    pub plain: bool,
+    /// `j`/`k` jump over headings instead of stopping on them
+    pub skip_headings: bool,
+    /// `j`/`k` past the end of the list go around to its other end
+    pub wrap_cursor: bool,
+    /// Long items take one row, cut off with `…`, instead of wrapping
+    pub truncate: bool,
+    /// Item numbers start at 0 instead of 1
+    pub number_from_zero: bool,
+    /// Side of its column an item number sits on
+    pub number_align: NumberAlign,
+    /// Columns item numbers take at least, so they don't shift as the list
+    /// grows past 9 or 99 items. Fitted to the length of the list when `None`
+    pub number_width: Option<usize>,
+    /// New items get an `@added:` date, and items without a date of their
+    /// own show how long ago that was
+    pub track_age: bool,
+    /// Leave out the row of key hints above the status line
+    pub hide_key_hints: bool,
+    /// Key that starts a multi-key binding
+    pub leader: Leader,
+    /// Longest wait for input before hook reports and signals are checked.
+    /// Timers (leader, autosave, file changes) wake the loop when they're due
+    pub poll_interval: PollInterval,
+    /// Ask before quitting without saving, even with no unsaved changes
+    pub confirm_quit: bool,
+    /// Quitting saves every list empty over a file that has items without
+    /// asking, and so does autosave
+    pub allow_empty_save: bool,
+    /// Keys and text of confirmation prompts
+    pub confirm: ConfirmPrompt,
+    /// Shell command run when a todo is completed
+    pub on_complete: Option<String>,
+    /// Shell command run when a new item is added
+    pub on_add: Option<String>,
+    /// Save this often while there are unsaved changes, off when `None`
+    pub autosave_interval: Option<Duration>,
+    /// Save after this many changes, off when `None`
+    pub autosave_edits: Option<usize>,
+    /// Layout of the TODO file, `tick` or `todo.txt`
+    pub format: Format,
+    /// Prefixes of todo and done lines in tick files
+    pub prefixes: Prefixes,
+    /// Actions bound to F2-F12 by `f<n> = <action>`
+    pub function_keys: HashMap<u8, Action>,
+}
+
+pub struct Theme {
+    /// Checkboxes in front of todos and dones
+    pub todo_indicator: String,
+    pub done_indicator: String,
+    /// Drawn dimmed in the indent of an item's wrapped rows, none by default
+    pub wrap_marker: Option<String>,
+    /// Emphasis of the selected item
+    pub selected: Highlight,
+    /// Text color of the selected item, the terminal's own by default
+    pub selected_fg: Option<Color>,
+    /// Emphasis of the item the cursor rests on in the other column, none
+    /// to leave it plain
+    pub inactive_selected: Option<Highlight>,
+    /// Emphasis of the active tab's header
+    pub header: Highlight,
+    /// Text color of todos past their due date
+    pub overdue: Color,
+    /// Text color of todos with priority A, B and C
+    pub priority_colors: [Color; 3],
+    /// Text color of `#tags`
+    pub tag: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            todo_indicator: globals::TODO_INDICATOR.to_string(),
+            done_indicator: globals::DONE_INDICATOR.to_string(),
+            wrap_marker: None,
+            selected: Highlight::Reverse,
+            selected_fg: None,
+            inactive_selected: Some(Highlight::Underline),
+            header: Highlight::Reverse,
+            overdue: Color::Red,
+            priority_colors: [Color::Magenta, Color::Yellow, Color::Cyan],
+            tag: Color::Blue,
+        }
+    }
+}
+
+pub struct ConfirmPrompt {
+    pub accept: KeyCode,
+    pub reject: KeyCode,
+    /// `{prompt}`, `{accept}` and `{reject}` are filled in when shown
+    pub template: String,
+}
+
+impl Default for ConfirmPrompt {
+    fn default() -> Self {
+        Self {
+            accept: KeyCode::Char('y'),
+            reject: KeyCode::Char('n'),
+            template: "{prompt} [{accept}/{reject}]".into(),
+        }
+    }
+}
+
+impl ConfirmPrompt {
+    pub fn render(&self, prompt: &str) -> String {
+        self.template
+            .replace("{prompt}", prompt)
+            .replace("{accept}", &key_name(self.accept))
+            .replace("{reject}", &key_name(self.reject))
+    }
+}
+
+pub struct Leader(pub char);
+
+impl Default for Leader {
+    fn default() -> Self {
+        Self(' ')
+    }
+}
+
+impl FromStr for Leader {
+    type Err = anyhow::Error;
+
+    /// Accepts `space` or any single character
+    fn from_str(s: &str) -> Result<Self> {
+        let mut chars = s.chars();
+        match (s, chars.next(), chars.next()) {
+            ("space", ..) => Ok(Self(' ')),
+            (_, Some(c), None) => Ok(Self(c)),
+            _ => bail!("Expected `space` or a single key, got {:?}", s),
+        }
+    }
+}
+
+/// End of a list items are moved to, the bottom by default
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListEnd {
+    Top,
+    #[default]
+    Bottom,
+}
+
+impl FromStr for ListEnd {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "top" => Ok(Self::Top),
+            "bottom" => Ok(Self::Bottom),
+            _ => bail!("Expected top or bottom, got {:?}", s),
+        }
+    }
+}
+
+/// Side item numbers are aligned to, the right by default so the dots line up
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberAlign {
+    Left,
+    #[default]
+    Right,
+}
+
+impl FromStr for NumberAlign {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "left" => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            _ => bail!("Expected left or right, got {:?}", s),
+        }
+    }
+}
+
+/// Longest wait of the main loop for input, in milliseconds, 100 by default
+pub struct PollInterval(pub Duration);
+
+impl Default for PollInterval {
+    fn default() -> Self {
+        Self(Duration::from_millis(100))
+    }
+}
+
+impl FromStr for PollInterval {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.parse() {
+            Ok(ms) if ms > 0 => Ok(Self(Duration::from_millis(ms))),
+            _ => bail!("Expected a number of milliseconds above 0, got {:?}", s),
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("tick").join("config"))
+    }
+
+    pub fn load() -> Result<Self> {
+        // https://no-color.org, the config file can still turn styling back on.
+        // A dumb terminal doesn't understand the escape codes for any of it
+        let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        let dumb = env::var_os("TERM").is_some_and(|term| term == "dumb");
+        let mut config = Self {
+            plain: no_color || dumb,
+            ..Self::default()
+        };
+        let Some(path) = Self::path() else {
+            return Ok(config);
+        };
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return Ok(config);
+        };
+
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let context = || format!("{}:{}", path.display(), i + 1);
+            let Some((key, value)) = line.split_once('=') else {
+                bail!("{}: expected `key = value`", context());
+            };
+            config.set(key.trim(), value.trim()).with_context(context)?;
+        }
+
+        // Lines of one list would load back into the other
+        if config.prefixes.todo == config.prefixes.done {
+            bail!(
+                "{}: todo_prefix and done_prefix must differ",
+                path.display()
+            );
+        }
+
+        Ok(config)
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "print_on_exit" => self.print_on_exit = parse_bool(value)?,
+            "read_only" => self.read_only = parse_bool(value)?,
+            "backup" => self.backup = parse_bool(value)?,
+            "save_log" => self.save_log = parse_bool(value)?,
+            "max_length" => self.max_length = parse_count(value)?,
+            "max_length_truncate" => self.max_length_truncate = parse_bool(value)?,
+            "warn_duplicates" => self.warn_duplicates = parse_bool(value)?,
+            "paste_join" => self.paste_join = parse_bool(value)?,
+            "complete_removes" => self.complete_removes = parse_bool(value)?,
+            "completion_log" => self.completion_log = parse_bool(value)?,
+            "complete_in_place" => self.complete_in_place = parse_bool(value)?,
+            "dones_newest_first" => self.dones_newest_first = parse_bool(value)?,
+            "completed_position" => self.completed_position = value.parse()?,
+            "reopened_position" => self.reopened_position = value.parse()?,
+            "complete_subtasks" => self.complete_subtasks = parse_bool(value)?,
+            "mouse" => self.mouse = parse_bool(value)?,
+            "swap_columns" => self.swap_columns = parse_bool(value)?,
+            "no_alt_screen" => self.no_alt_screen = parse_bool(value)?,
+            "plain" => self.plain = parse_bool(value)?,
+            "highlight" => self.theme.selected = value.parse()?,
+            "inactive_highlight" => {
+                self.theme.inactive_selected = match value {
+                    "none" => None,
+                    value => Some(value.parse()?),
+                }
+            }
+            "highlight_fg" => self.theme.selected_fg = Some(parse_color(value)?),
+            "header_highlight" => self.theme.header = value.parse()?,
+            "overdue_color" => self.theme.overdue = parse_color(value)?,
+            "priority_colors" => self.theme.priority_colors = parse_colors(value)?,
+            "tag_color" => self.theme.tag = parse_color(value)?,
+            "skip_headings" => self.skip_headings = parse_bool(value)?,
+            "wrap_cursor" => self.wrap_cursor = parse_bool(value)?,
+            "truncate" => self.truncate = parse_bool(value)?,
+            "number_from_zero" => self.number_from_zero = parse_bool(value)?,
+            "number_align" => self.number_align = value.parse()?,
+            "number_width" => self.number_width = parse_count(value)?,
+            "track_age" => self.track_age = parse_bool(value)?,
+            "hide_key_hints" => self.hide_key_hints = parse_bool(value)?,
+            "leader" => self.leader = value.parse()?,
+            "poll_interval" => self.poll_interval = value.parse()?,
+            "confirm_quit" => self.confirm_quit = parse_bool(value)?,
+            "allow_empty_save" => self.allow_empty_save = parse_bool(value)?,
+            "confirm_accept" => self.confirm.accept = parse_key(value)?,
+            "confirm_reject" => self.confirm.reject = parse_key(value)?,
+            "confirm_template" => self.confirm.template = value.to_string(),
+            "on_complete" => self.on_complete = Some(value.to_string()),
+            "on_add" => self.on_add = Some(value.to_string()),
+            "autosave_interval" => {
+                self.autosave_interval = parse_count(value)?.map(Duration::from_secs)
+            }
+            "autosave_edits" => self.autosave_edits = parse_count(value)?,
+            "format" => self.format = value.parse()?,
+            "todo_prefix" => self.prefixes.todo = parse_text(value)?,
+            "done_prefix" => self.prefixes.done = parse_text(value)?,
+            "todo_indicator" => self.theme.todo_indicator = parse_text(value)?,
+            "done_indicator" => self.theme.done_indicator = parse_text(value)?,
+            "wrap_marker" => {
+                self.theme.wrap_marker = Some(parse_text(value)?).filter(|m| !m.is_empty())
+            }
+            _ => {
+                let Some(n) = function_key(key) else {
+                    bail!("Unknown setting {:?}", key);
+                };
+                self.function_keys.insert(n, value.parse()?);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Number of a rebindable function key setting, `f2` to `f12`
+fn function_key(key: &str) -> Option<u8> {
+    let n = key.strip_prefix('f')?.parse().ok()?;
+    (2..=12).contains(&n).then_some(n)
+}
+
+/// Accepts `enter`, `esc`, `space` or any single character
+fn parse_key(value: &str) -> Result<KeyCode> {
+    let mut chars = value.chars();
+    Ok(match (value, chars.next(), chars.next()) {
+        ("enter", ..) => KeyCode::Enter,
+        ("esc", ..) => KeyCode::Esc,
+        ("space", ..) => KeyCode::Char(' '),
+        (_, Some(c), None) => KeyCode::Char(c),
+        _ => bail!(
+            "Expected enter, esc, space or a single key, got {:?}",
+            value
+        ),
+    })
+}
+
+fn key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "space".into(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Enter => "Enter".into(),
+        KeyCode::Esc => "Esc".into(),
+        key => key.to_string(),
+    }
+}
+
+/// Non-empty text, in double quotes to keep leading or trailing spaces
+fn parse_text(value: &str) -> Result<String> {
+    let text = value
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .unwrap_or(value);
+    if text.is_empty() {
+        bail!("Expected some text, got an empty value");
+    }
+    Ok(text.to_string())
+}
+
+/// Three colors separated by spaces, one per priority
+fn parse_colors(value: &str) -> Result<[Color; 3]> {
+    let colors = value
+        .split_whitespace()
+        .map(parse_color)
+        .collect::<Result<Vec<_>>>()?;
+    colors
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Expected three colors, got {:?}", value))
+}
+
+/// A positive number, `0` turns the setting off
+fn parse_count<T: FromStr + Default + PartialEq>(value: &str) -> Result<Option<T>> {
+    let Ok(n) = value.parse::<T>() else {
+        bail!("Expected a number, got {:?}", value);
+    };
+    Ok((n != T::default()).then_some(n))
+}
+
+fn parse_bool(value: &str) -> Result<bool> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => bail!("Expected true or false, got {:?}", value),
+    }
+}
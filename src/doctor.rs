@@ -0,0 +1,81 @@
+use std::io::{self, IsTerminal};
+use std::path::Path;
+
+use crossterm::{style, terminal};
+use tick::file::{check_writable, get_todos_dones};
+
+use crate::config::Config;
+
+/// Checks the setup without entering the TUI, returns whether every check passed
+pub fn run(file_path: &Path) -> bool {
+    let checks = [
+        ("config", check_config()),
+        ("file", check_file(file_path)),
+        ("terminal", check_terminal()),
+    ];
+
+    let mut all_ok = true;
+    for (name, result) in checks {
+        match result {
+            Ok(info) => println!("[ok]   {name}: {info}"),
+            Err(e) => {
+                println!("[FAIL] {name}: {e:#}");
+                all_ok = false;
+            }
+        }
+    }
+    all_ok
+}
+
+fn check_config() -> anyhow::Result<String> {
+    let Some(path) = Config::path() else {
+        return Ok("no config directory, using defaults".into());
+    };
+    if !path.exists() {
+        return Ok(format!("{} not found, using defaults", path.display()));
+    }
+
+    Config::load()?;
+    Ok(format!("{} parsed", path.display()))
+}
+
+fn check_file(file_path: &Path) -> anyhow::Result<String> {
+    check_writable(file_path)?;
+    if file_path.exists() {
+        // A broken config is reported by its own check
+        let config = Config::load().unwrap_or_default();
+        let loaded = get_todos_dones(file_path, config.format, &config.prefixes)?;
+
+        let mut info = format!(
+            "{} is writable ({} todos, {} dones)",
+            file_path.display(),
+            loaded.todos.len(),
+            loaded.dones.len()
+        );
+        if loaded.migrated > 0 {
+            info += &format!(", {} line(s) with a legacy prefix", loaded.migrated);
+        }
+        let unrecognized = loaded.unrecognized_lines();
+        if !unrecognized.is_empty() {
+            let numbers: Vec<_> = unrecognized.iter().map(usize::to_string).collect();
+            info += &format!(
+                ", {} unrecognized line(s) kept as is (lines {})",
+                unrecognized.len(),
+                numbers.join(", ")
+            );
+        }
+        return Ok(info);
+    }
+
+    Ok(format!("{} will be created on save", file_path.display()))
+}
+
+fn check_terminal() -> anyhow::Result<String> {
+    if !io::stdout().is_terminal() {
+        anyhow::bail!("stdout is not a terminal");
+    }
+
+    let (cols, rows) = terminal::size()?;
+    let colors = style::available_color_count();
+    Ok(format!("{cols}x{rows}, {colors} colors"))
+}
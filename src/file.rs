@@ -0,0 +1,825 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time;
+
+use anyhow::Result;
+
+use crate::item::{
+    Format, HEADING_MARK, Item, Kind, Prefixes, SUBTASK_INDENT, SUBTASK_MARK, clean_text,
+    heading_text, parse_legacy, subtask_text,
+};
+
+/// One line of a TODO file as it was loaded, so saving puts every line back
+/// where it was
+#[derive(Clone, PartialEq, Eq)]
+pub enum Line {
+    /// An item of the list of this kind, with its text as kept in the list
+    Item(Kind, String),
+    /// A comment or any other line that isn't an item, kept as it was
+    Other(String),
+    Blank,
+}
+
+/// Contents of a TODO file
+#[derive(Default)]
+pub struct TodoFile {
+    pub todos: Vec<String>,
+    pub dones: Vec<String>,
+    /// Someday/maybe items, not actionable yet
+    pub someday: Vec<String>,
+    /// Archived items, kept apart by the list they were archived from
+    pub archived_todos: Vec<String>,
+    pub archived_dones: Vec<String>,
+    /// Every line of the file in order, the items standing in for their lists
+    pub layout: Vec<Line>,
+    /// Items read from lines with a legacy prefix (see [`parse_legacy`]),
+    /// which saving rewrites with the current ones
+    pub migrated: usize,
+}
+
+impl TodoFile {
+    /// Lines that are neither items nor comments, most likely mistakes
+    pub fn unrecognized(&self) -> usize {
+        self.unrecognized_lines().len()
+    }
+
+    /// 1-based numbers of the lines counted by [`unrecognized`](Self::unrecognized)
+    pub fn unrecognized_lines(&self) -> Vec<usize> {
+        let lines = self.layout.iter().enumerate();
+        lines
+            .filter(|(_, line)| matches!(line, Line::Other(other) if !other.starts_with('#')))
+            .map(|(i, _)| i + 1)
+            .collect()
+    }
+
+    /// Adds `item` to the list of its kind, headings going into the list they
+    /// head. Returns the entry as stored in the list
+    pub fn push(&mut self, item: Item) -> &str {
+        let heading = || format!("{}{}", HEADING_MARK, item.text);
+        let (list, entry) = match item.kind {
+            Kind::Todo => (&mut self.todos, item.text),
+            Kind::Done => (&mut self.dones, item.text),
+            Kind::Someday => (&mut self.someday, item.text),
+            Kind::ArchivedTodo => (&mut self.archived_todos, item.text),
+            Kind::ArchivedDone => (&mut self.archived_dones, item.text),
+            Kind::TodoHeading => (&mut self.todos, heading()),
+            Kind::DoneHeading => (&mut self.dones, heading()),
+        };
+        list.push(entry);
+        &list[list.len() - 1]
+    }
+
+    /// The lists paired with their kind, as `save_to_file` takes them
+    pub fn sections(&self) -> [(Kind, &[String]); 5] {
+        [
+            (Kind::Todo, &self.todos),
+            (Kind::Done, &self.dones),
+            (Kind::Someday, &self.someday),
+            (Kind::ArchivedTodo, &self.archived_todos),
+            (Kind::ArchivedDone, &self.archived_dones),
+        ]
+    }
+
+    /// Status message about migrated and unrecognized lines, if there are any
+    pub fn load_msg(&self) -> Option<String> {
+        let mut notes = Vec::new();
+        if self.migrated > 0 {
+            let count = self.migrated;
+            notes.push(format!(
+                "{count} line(s) with a legacy prefix, updated on save"
+            ));
+        }
+        let lines = self.unrecognized_lines();
+        if !lines.is_empty() {
+            notes.push(format!(
+                "{} unrecognized line(s) kept as they are ({})",
+                lines.len(),
+                line_list(&lines)
+            ));
+        }
+        (!notes.is_empty()).then(|| notes.join(", "))
+    }
+}
+
+/// Where the lines of a TODO file are kept. [`FileBackend`] reads and writes
+/// a file on disk, [`MemoryBackend`] a string, for tests and tools that never
+/// touch the disk
+pub trait Backend {
+    /// Loads the lists, empty when nothing was stored yet
+    fn load(&self, format: Format, prefixes: &Prefixes) -> Result<TodoFile>;
+
+    /// Stores the lists the way [`save_to_file`] does, returning the layout
+    /// written
+    fn save(
+        &mut self,
+        format: Format,
+        prefixes: &Prefixes,
+        layout: &[Line],
+        sections: &[(Kind, &[String])],
+    ) -> io::Result<Vec<Line>>;
+}
+
+/// A TODO file on disk, loaded with [`get_todos_dones`] and saved with
+/// [`save_to_file`]
+pub struct FileBackend {
+    pub path: PathBuf,
+    /// Copy the file to `<name>.bak` before each save
+    pub backup: bool,
+}
+
+impl FileBackend {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            backup: false,
+        }
+    }
+}
+
+impl Backend for FileBackend {
+    fn load(&self, format: Format, prefixes: &Prefixes) -> Result<TodoFile> {
+        get_todos_dones(&self.path, format, prefixes)
+    }
+
+    fn save(
+        &mut self,
+        format: Format,
+        prefixes: &Prefixes,
+        layout: &[Line],
+        sections: &[(Kind, &[String])],
+    ) -> io::Result<Vec<Line>> {
+        save_to_file(&self.path, format, prefixes, layout, sections, self.backup)
+    }
+}
+
+/// A TODO file kept in a string, `None` standing for one that doesn't exist
+/// yet. Saving follows the rules of [`save_to_file`], so nothing is created
+/// when there is nothing to save
+#[derive(Default)]
+pub struct MemoryBackend {
+    pub contents: Option<String>,
+}
+
+impl Backend for MemoryBackend {
+    fn load(&self, format: Format, prefixes: &Prefixes) -> Result<TodoFile> {
+        let contents = self.contents.as_deref().unwrap_or_default();
+        Ok(read_todos(contents.as_bytes(), format, prefixes))
+    }
+
+    fn save(
+        &mut self,
+        format: Format,
+        prefixes: &Prefixes,
+        layout: &[Line],
+        sections: &[(Kind, &[String])],
+    ) -> io::Result<Vec<Line>> {
+        if self.contents.is_none() && nothing_to_save(layout, sections) {
+            return Ok(Vec::new());
+        }
+        let mut out = Vec::new();
+        let written = write_todos(&mut out, format, prefixes, layout, sections)?;
+        self.contents = Some(String::from_utf8(out).map_err(io::Error::other)?);
+        Ok(written)
+    }
+}
+
+/// `line 5` or `lines 5, 12, 40`, the first few numbers only
+fn line_list(lines: &[usize]) -> String {
+    const SHOWN: usize = 5;
+    let numbers: Vec<_> = lines.iter().take(SHOWN).map(usize::to_string).collect();
+    let more = if lines.len() > SHOWN { ", …" } else { "" };
+    match lines {
+        [_] => format!("line {}", numbers[0]),
+        _ => format!("lines {}{more}", numbers.join(", ")),
+    }
+}
+
+/// Loads the items from `path`, noting in `layout` where each line was so
+/// blank lines, comments and any other line that isn't an item stay in place.
+/// Items on lines indented by [`SUBTASK_INDENT`] are loaded as subtasks
+pub fn get_todos_dones<P>(path: P, format: Format, prefixes: &Prefixes) -> Result<TodoFile>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    if path.is_dir() {
+        anyhow::bail!("Provided path is a directory: {:?}", path.display());
+    }
+
+    let Ok(file) = File::open(path) else {
+        return Ok(Default::default());
+    };
+
+    Ok(read_todos(io::BufReader::new(file), format, prefixes))
+}
+
+/// Parses a TODO file from `reader`, the way [`get_todos_dones`] does for a
+/// file on disk. Reading stops at the first line that isn't valid UTF-8
+pub fn read_todos<R: BufRead>(reader: R, format: Format, prefixes: &Prefixes) -> TodoFile {
+    let mut loaded = TodoFile::default();
+
+    for line in reader.lines().map_while(Result::ok) {
+        if line.trim().is_empty() {
+            loaded.layout.push(Line::Blank);
+            continue;
+        }
+        let (subtask, body) = match line.strip_prefix(SUBTASK_INDENT) {
+            Some(body) => (true, body),
+            None => (false, line.as_str()),
+        };
+        // Only tick files have prefixes to migrate, todo.txt takes any line
+        let legacy = || {
+            let item = parse_legacy(body).filter(|_| format == Format::Tick)?;
+            loaded.migrated += 1;
+            Some(item)
+        };
+        let item = format.parse(body, prefixes).ok().or_else(legacy);
+        // Headings are never indented, such a line is kept as it is
+        match item.filter(|item| !subtask || item.kind.list() == item.kind) {
+            Some(mut item) => {
+                // Control characters would break the marks kept in entries,
+                // or the line once saved
+                item.text = clean_text(&item.text);
+                if subtask {
+                    item.text.insert(0, SUBTASK_MARK);
+                }
+                let kind = item.kind.list();
+                let entry = loaded.push(item).to_string();
+                loaded.layout.push(Line::Item(kind, entry));
+            }
+            None => loaded.layout.push(Line::Other(line)),
+        }
+    }
+    loaded
+}
+
+/// Writes every item on its own newline-terminated line, preceded by the
+/// prefix of the section it belongs to. Items are trimmed, and dropped when
+/// nothing is left. Subtasks are indented by [`SUBTASK_INDENT`].
+///
+/// The lines of `layout` are written back in order, each item on the line it
+/// was loaded from (see `place_items`). Lists without a line in `layout` go
+/// at the end, in section order. Lines of deleted items disappear, the
+/// comments and blank lines around them stay. Returns the layout written.
+///
+/// An existing file left without items or comments is truncated to zero
+/// bytes, never left with lone newlines. Nothing is created when every list
+/// is empty and the file doesn't exist yet.
+///
+/// The lines go to a sibling `<name>.tmp` file first, which then replaces the
+/// real one, so a crash mid-write never leaves a half written TODO file.
+/// With `backup`, the file as it was is first copied to `<name>.bak`,
+/// replacing the previous backup. A file that doesn't exist yet has none.
+pub fn save_to_file<P>(
+    path: P,
+    format: Format,
+    prefixes: &Prefixes,
+    layout: &[Line],
+    sections: &[(Kind, &[String])],
+    backup: bool,
+) -> io::Result<Vec<Line>>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    if nothing_to_save(layout, sections) && !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    // Replace the file a symlink points to, not the link itself
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+    if backup && path.exists() {
+        let mut bak_name = path.file_name().unwrap_or_default().to_os_string();
+        bak_name.push(".bak");
+        fs::copy(&path, path.with_file_name(bak_name))?;
+    }
+
+    let written =
+        write_sections(&tmp_path, format, prefixes, layout, sections).and_then(|layout| {
+            if let Ok(meta) = fs::metadata(&path) {
+                fs::set_permissions(&tmp_path, meta.permissions())?;
+            }
+            fs::rename(&tmp_path, &path)?;
+            Ok(layout)
+        });
+    if written.is_err() {
+        _ = fs::remove_file(&tmp_path);
+    }
+    written
+}
+
+/// Whether saving would write no line at all
+fn nothing_to_save(layout: &[Line], sections: &[(Kind, &[String])]) -> bool {
+    layout.is_empty() && sections.iter().all(|(_, items)| items.is_empty())
+}
+
+fn write_sections(
+    path: &Path,
+    format: Format,
+    prefixes: &Prefixes,
+    layout: &[Line],
+    sections: &[(Kind, &[String])],
+) -> io::Result<Vec<Line>> {
+    let mut file = io::BufWriter::new(File::create(path)?);
+    let written = write_todos(&mut file, format, prefixes, layout, sections)?;
+
+    // Everything must be on disk before the rename makes it the real file
+    file.into_inner()?.sync_all()?;
+    Ok(written)
+}
+
+/// Writes the lines [`save_to_file`] would save to `out`, returning the
+/// layout written. Unlike `save_to_file` it always writes, even with nothing
+/// to save, and neither syncs nor flushes `out`
+pub fn write_todos(
+    mut out: impl Write,
+    format: Format,
+    prefixes: &Prefixes,
+    layout: &[Line],
+    sections: &[(Kind, &[String])],
+) -> io::Result<Vec<Line>> {
+    let mut written = Vec::new();
+    let keep_blanks = sections.iter().any(|(_, items)| !items.is_empty())
+        || layout.iter().any(|line| matches!(line, Line::Other(_)));
+
+    // The items going on each of the lines every list had in the file
+    let mut placed: Vec<_> = sections
+        .iter()
+        .map(|&(kind, items)| {
+            let slots: Vec<_> = layout
+                .iter()
+                .filter_map(|line| match line {
+                    Line::Item(k, entry) if *k == kind => Some(entry.as_str()),
+                    _ => None,
+                })
+                .collect();
+            // Lists the file had no line for are written at the end instead
+            let placed = if slots.is_empty() {
+                Vec::new()
+            } else {
+                place_items(&slots, items)
+            };
+            (kind, placed.into_iter())
+        })
+        .collect();
+
+    for line in layout {
+        match line {
+            Line::Blank if keep_blanks => {
+                writeln!(out)?;
+                written.push(Line::Blank);
+            }
+            Line::Blank => {}
+            Line::Other(other) => {
+                writeln!(out, "{}", other)?;
+                written.push(line.clone());
+            }
+            Line::Item(kind, _) => {
+                let Some((_, slots)) = placed.iter_mut().find(|(k, _)| k == kind) else {
+                    continue;
+                };
+                for entry in slots.next().unwrap_or_default() {
+                    write_item(&mut out, format, prefixes, *kind, entry, &mut written)?;
+                }
+            }
+        }
+    }
+    for &(kind, items) in sections {
+        if !layout
+            .iter()
+            .any(|line| matches!(line, Line::Item(k, _) if *k == kind))
+        {
+            for entry in items {
+                write_item(&mut out, format, prefixes, kind, entry, &mut written)?;
+            }
+        }
+    }
+    Ok(written)
+}
+
+/// Spreads `items` over the lines their list had in the file, `slots` being
+/// the entries those lines held. Returns the items that go on each line.
+///
+/// Items still in the file keep their line, matched in order. Between two of
+/// them, the other items (new or edited) take the unmatched lines in between
+/// one each, and any extra follow the last of those, or the matched item
+/// before them. Items before any match go on the first line. `slots` must not
+/// be empty
+fn place_items<'a>(slots: &[&str], items: &'a [String]) -> Vec<Vec<&'a str>> {
+    let mut placed = vec![Vec::new(); slots.len()];
+    let mut prev: Option<(usize, usize)> = None;
+    let anchors = common_subsequence(slots, items);
+
+    for anchor in anchors.into_iter().map(Some).chain([None]) {
+        let (slot_from, item_from) = prev.map_or((0, 0), |(s, i)| (s + 1, i + 1));
+        let (slot_to, item_to) = anchor.unwrap_or((slots.len(), items.len()));
+        let free = slot_from..slot_to;
+        for (n, item) in items[item_from..item_to].iter().enumerate() {
+            let slot = if n < free.len() {
+                free.start + n
+            } else if !free.is_empty() {
+                free.end - 1
+            } else {
+                prev.or(anchor).map_or(0, |(s, _)| s)
+            };
+            placed[slot].push(item.as_str());
+        }
+        if let Some((s, i)) = anchor {
+            placed[s].push(items[i].as_str());
+            prev = anchor;
+        }
+    }
+    placed
+}
+
+/// Matching pairs of lines past which [`common_subsequence`] goes back to
+/// Hirschberg's algorithm. Only lists repeating the same lines a lot get there
+const MATCH_LIMIT: usize = 1 << 20;
+
+/// Index pairs of a longest common subsequence of `a` and `b`
+fn common_subsequence(a: &[&str], b: &[String]) -> Vec<(usize, usize)> {
+    common_subsequence_within(a, b, MATCH_LIMIT)
+}
+
+/// [`common_subsequence`], trying the pairs of matching lines one by one
+/// while there are at most `match_limit` of them
+fn common_subsequence_within(a: &[&str], b: &[String], match_limit: usize) -> Vec<(usize, usize)> {
+    // The start and end usually match, only what changed in between is compared
+    let prefix = a.iter().zip(b).take_while(|(x, y)| **x == *y).count();
+    let suffix = (a[prefix..].iter().rev())
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| **x == *y)
+        .count();
+    // Lines are compared a lot, as numbers standing for each distinct one
+    let mut ids = HashMap::new();
+    let mut id = |line: &str| {
+        let next = ids.len() as u32;
+        *ids.entry(line.to_string()).or_insert(next)
+    };
+    let a_mid: Vec<_> = a[prefix..a.len() - suffix].iter().map(|x| id(x)).collect();
+    let b_mid: Vec<_> = b[prefix..b.len() - suffix].iter().map(|y| id(y)).collect();
+
+    let mut pairs: Vec<_> = (0..prefix).map(|k| (k, k)).collect();
+    // Where each line is in `b`, last first
+    let mut places = vec![Vec::new(); ids.len()];
+    for (j, &y) in b_mid.iter().enumerate().rev() {
+        places[y as usize].push(j);
+    }
+    let matches: usize = a_mid.iter().map(|&x| places[x as usize].len()).sum();
+    if matches <= match_limit {
+        hunt_szymanski(&a_mid, &places, (prefix, prefix), &mut pairs);
+    } else {
+        hirschberg(&a_mid, &b_mid, (prefix, prefix), &mut pairs);
+    }
+    pairs.extend((0..suffix).map(|k| (a.len() - suffix + k, b.len() - suffix + k)));
+    pairs
+}
+
+/// Pushes the index pairs of a longest common subsequence of `a` and the
+/// lines `places` lists the indices of, shifted by `offset`, in order.
+/// Hunt and Szymanski's algorithm: each pair of matching lines extends the
+/// longest chain of pairs it can follow, kept as the smallest index in the
+/// second list ending a chain of each length. It takes time in the number
+/// of pairs rather than of lines squared, so reordering a long list of
+/// distinct items is quick
+fn hunt_szymanski(
+    a: &[u32],
+    places: &[Vec<usize>],
+    offset: (usize, usize),
+    pairs: &mut Vec<(usize, usize)>,
+) {
+    // Every pair tried, with the one before it in its chain
+    let mut links: Vec<(usize, usize, Option<usize>)> = Vec::new();
+    // Index in the second list and link ending the best chain of each length
+    let mut ends: Vec<(usize, usize)> = Vec::new();
+    for (i, &x) in a.iter().enumerate() {
+        // Going down, so a line never follows another pair of the same `i`
+        for &j in &places[x as usize] {
+            let len = ends.partition_point(|&(end, _)| end < j);
+            let prev = len.checked_sub(1).map(|k| ends[k].1);
+            links.push((i, j, prev));
+            let end = (j, links.len() - 1);
+            match ends.get_mut(len) {
+                Some(slot) => *slot = end,
+                None => ends.push(end),
+            }
+        }
+    }
+
+    let start = pairs.len();
+    let mut link = ends.last().map(|&(_, link)| link);
+    while let Some(k) = link {
+        let (i, j, prev) = links[k];
+        pairs.push((offset.0 + i, offset.1 + j));
+        link = prev;
+    }
+    pairs[start..].reverse();
+}
+
+/// Pushes the index pairs of a longest common subsequence of `a` and `b`,
+/// shifted by `offset`, in order. Hirschberg's algorithm: the middle of `a`
+/// is matched to the split of `b` where the lengths of both halves add up
+/// best, then each half is solved on its own. It takes time `a.len() *
+/// b.len()` but only space `b.len()`, so reordering a long list doesn't
+/// allocate a table of every pair of lines
+fn hirschberg(a: &[u32], b: &[u32], offset: (usize, usize), pairs: &mut Vec<(usize, usize)>) {
+    if a.is_empty() || b.is_empty() {
+        return;
+    }
+    if let [line] = a {
+        if let Some(j) = b.iter().position(|other| other == line) {
+            pairs.push((offset.0, offset.1 + j));
+        }
+        return;
+    }
+
+    let mid = a.len() / 2;
+    let front = lcs_lengths(a[..mid].iter(), b.iter());
+    let back = lcs_lengths(a[mid..].iter().rev(), b.iter().rev());
+    let split = (0..=b.len())
+        .max_by_key(|&k| (front[k] + back[b.len() - k], Reverse(k)))
+        .unwrap_or(0);
+
+    hirschberg(&a[..mid], &b[..split], offset, pairs);
+    let offset = (offset.0 + mid, offset.1 + split);
+    hirschberg(&a[mid..], &b[split..], offset, pairs);
+}
+
+/// Lengths of the longest common subsequence of all of `a` and each start of
+/// `b`: the one with its first `j` lines at index `j`
+fn lcs_lengths<'a>(
+    a: impl Iterator<Item = &'a u32>,
+    b: impl Iterator<Item = &'a u32> + Clone,
+) -> Vec<u32> {
+    let mut row = vec![0; b.clone().count() + 1];
+    for x in a {
+        // The previous row's value left of the one being replaced
+        let mut diagonal = 0;
+        for (j, y) in b.clone().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if x == y {
+                diagonal + 1
+            } else {
+                above.max(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row
+}
+
+/// Writes one list entry, noting its line in `written` unless it's dropped
+fn write_item(
+    file: &mut impl Write,
+    format: Format,
+    prefixes: &Prefixes,
+    kind: Kind,
+    entry: &str,
+    written: &mut Vec<Line>,
+) -> io::Result<()> {
+    // Headings only survive in lists that can hold them
+    let item = match heading_text(entry) {
+        Some(text) => Item {
+            kind: kind.heading().unwrap_or(kind),
+            text,
+        },
+        None => Item { kind, text: entry },
+    };
+    let (indent, text) = match subtask_text(item.text) {
+        Some(text) => (SUBTASK_INDENT, text),
+        None => ("", item.text),
+    };
+
+    // Blank items only live while being edited, they never reach the file.
+    // Neither do line breaks, which would make two items of one
+    let text = clean_text(text);
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(());
+    }
+    let line = format.line(&Item { text, ..item }, prefixes);
+    writeln!(file, "{}{}", indent, line)?;
+    written.push(Line::Item(kind, entry.to_string()));
+    Ok(())
+}
+
+/// Fails with the reason `path` couldn't be saved: the file isn't writable,
+/// or the directory saving puts its temp file in isn't
+pub fn check_writable<P>(path: P) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if path.exists() {
+        OpenOptions::new().append(true).open(&path)?;
+    }
+
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let probe = dir.join(".tick-probe");
+    fs::write(&probe, "")?;
+    fs::remove_file(&probe)
+}
+
+pub fn modified<P>(path: P) -> Option<time::SystemTime>
+where
+    P: AsRef<Path>,
+{
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Loads `contents` and saves the lists straight back
+    fn round_trip(contents: &str, format: Format, prefixes: &Prefixes) -> String {
+        let mut backend = MemoryBackend {
+            contents: Some(contents.to_string()),
+        };
+        let loaded = backend.load(format, prefixes).unwrap();
+        backend
+            .save(format, prefixes, &loaded.layout, &loaded.sections())
+            .unwrap();
+        backend.contents.unwrap()
+    }
+
+    #[test]
+    fn headings_and_subtasks_round_trip() {
+        let contents = "\
+TODO HEADING: Work
+TODO: write report
+  TODO: outline
+  TODO: draft
+DONE HEADING: Shipped
+DONE: release
+";
+        let prefixes = Prefixes::default();
+        assert_eq!(round_trip(contents, Format::Tick, &prefixes), contents);
+
+        let loaded = read_todos(contents.as_bytes(), Format::Tick, &prefixes);
+        assert_eq!(heading_text(&loaded.todos[0]), Some("Work"));
+        assert_eq!(subtask_text(&loaded.todos[2]), Some("outline"));
+        assert_eq!(heading_text(&loaded.dones[0]), Some("Shipped"));
+    }
+
+    #[test]
+    fn control_characters_are_dropped_on_load() {
+        let contents = "TODO: a\tb\u{7}c\rd\n";
+        let loaded = read_todos(contents.as_bytes(), Format::Tick, &Prefixes::default());
+        assert_eq!(loaded.todos, ["a bcd"]);
+    }
+
+    #[test]
+    fn dates_round_trip() {
+        let contents = "\
+TODO: pay rent @due:2026-11-01
+DONE: call mom @due:2026-10-01 @done:2026-09-30
+";
+        let prefixes = Prefixes::default();
+        assert_eq!(round_trip(contents, Format::Tick, &prefixes), contents);
+
+        let todo_txt = "pay rent @due:2026-11-01\nx 2026-09-30 call mom\n";
+        assert_eq!(round_trip(todo_txt, Format::TodoTxt, &prefixes), todo_txt);
+    }
+
+    #[test]
+    fn custom_prefixes_round_trip() {
+        let prefixes = Prefixes {
+            todo: "- [ ] ".into(),
+            done: "- [x] ".into(),
+        };
+        let contents = "- [ ] buy milk\n- [x] walk dog\nSOMEDAY: learn piano\n";
+        assert_eq!(round_trip(contents, Format::Tick, &prefixes), contents);
+
+        let loaded = read_todos(contents.as_bytes(), Format::Tick, &prefixes);
+        assert_eq!(loaded.todos, ["buy milk"]);
+        assert_eq!(loaded.dones, ["walk dog"]);
+        assert_eq!(loaded.someday, ["learn piano"]);
+    }
+
+    #[test]
+    fn comments_and_blank_lines_stay_in_place() {
+        let contents = "# today\nTODO: one\n\n# later\nTODO: two\nDONE: three\n";
+        let prefixes = Prefixes::default();
+        assert_eq!(round_trip(contents, Format::Tick, &prefixes), contents);
+    }
+
+    #[test]
+    fn saved_lists_load_back() {
+        let prefixes = Prefixes::default();
+        let todos = vec!["one".to_string(), format!("{SUBTASK_MARK}two")];
+        let dones = vec!["three @done:2026-01-02".to_string()];
+        let mut backend = MemoryBackend::default();
+        backend
+            .save(
+                Format::Tick,
+                &prefixes,
+                &[],
+                &[(Kind::Todo, &todos), (Kind::Done, &dones)],
+            )
+            .unwrap();
+
+        let loaded = backend.load(Format::Tick, &prefixes).unwrap();
+        assert_eq!(loaded.todos, todos);
+        assert_eq!(loaded.dones, dones);
+    }
+
+    #[test]
+    fn deleting_every_item_saves_an_empty_file() {
+        let prefixes = Prefixes::default();
+        let mut backend = MemoryBackend {
+            contents: Some("TODO: a\nTODO: b\nDONE: c\nSOMEDAY: d\n".to_string()),
+        };
+        let loaded = backend.load(Format::Tick, &prefixes).unwrap();
+        let emptied = TodoFile::default();
+        let written = backend
+            .save(Format::Tick, &prefixes, &loaded.layout, &emptied.sections())
+            .unwrap();
+
+        assert!(written.is_empty());
+        assert_eq!(backend.contents.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn empty_lists_create_nothing() {
+        let prefixes = Prefixes::default();
+        let mut backend = MemoryBackend::default();
+        let written = backend
+            .save(
+                Format::Tick,
+                &prefixes,
+                &[],
+                &TodoFile::default().sections(),
+            )
+            .unwrap();
+
+        assert!(written.is_empty());
+        assert_eq!(backend.contents, None);
+        let loaded = backend.load(Format::Tick, &prefixes).unwrap();
+        assert!(loaded.todos.is_empty() && loaded.layout.is_empty());
+    }
+
+    /// Length of a longest common subsequence, from the full table
+    fn lcs_len(a: &[&str], b: &[String]) -> usize {
+        let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+        for i in 0..a.len() {
+            for j in 0..b.len() {
+                table[i + 1][j + 1] = if a[i] == b[j] {
+                    table[i][j] + 1
+                } else {
+                    table[i][j + 1].max(table[i + 1][j])
+                };
+            }
+        }
+        table[a.len()][b.len()]
+    }
+
+    #[test]
+    fn common_subsequence_is_longest() {
+        // Lines drawn from a few words, so there are many repeats
+        let mut seed = 7u32;
+        let mut lines = |n: usize| -> Vec<String> {
+            (0..n)
+                .map(|_| {
+                    seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                    ["a", "b", "c", "d"][(seed >> 16) as usize % 4].to_string()
+                })
+                .collect()
+        };
+        for (n, m) in [(0, 3), (3, 0), (1, 1), (5, 8), (13, 7), (30, 30)] {
+            let a = lines(n);
+            let a: Vec<_> = a.iter().map(String::as_str).collect();
+            let b = lines(m);
+            // Pair by pair, and with Hirschberg's algorithm
+            for match_limit in [MATCH_LIMIT, 0] {
+                let pairs = common_subsequence_within(&a, &b, match_limit);
+                assert_eq!(pairs.len(), lcs_len(&a, &b));
+                assert!(pairs.iter().all(|&(i, j)| a[i] == b[j]));
+                assert!(pairs.windows(2).all(|w| w[0].0 < w[1].0 && w[0].1 < w[1].1));
+            }
+        }
+    }
+
+    #[test]
+    fn reordering_a_long_list_keeps_lines() {
+        let items: Vec<_> = (0..2000).map(|i| format!("item {i:04}")).collect();
+        let slots: Vec<_> = items.iter().map(String::as_str).collect();
+        let mut sorted = items.clone();
+        sorted.reverse();
+
+        let pairs = common_subsequence(&slots, &sorted);
+        assert_eq!(pairs.len(), 1);
+        let placed = place_items(&slots, &sorted);
+        assert_eq!(placed.iter().map(Vec::len).sum::<usize>(), sorted.len());
+    }
+}
@@ -0,0 +1,199 @@
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+use crossterm::style::{
+    Attribute, Color, Print, SetAttribute, SetBackgroundColor, SetForegroundColor,
+};
+use crossterm::terminal::{Clear, ClearType};
+use crossterm::{cursor, queue};
+use tick::text::grapheme_width;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::helpers::Highlight;
+
+/// How a cell is drawn, taken from the frame's settings when it was written
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+struct Style {
+    highlight: Option<Highlight>,
+    dim: bool,
+    foreground: Option<Color>,
+}
+
+/// One screen cell, holding a whole grapheme cluster so combining marks and
+/// joined emoji stay together. The right half of a wide glyph holds none
+#[derive(Clone, PartialEq, Eq)]
+struct Cell {
+    glyph: Option<Cow<'static, str>>,
+    style: Style,
+}
+
+const BLANK: Cell = Cell {
+    glyph: Some(Cow::Borrowed(" ")),
+    style: Style {
+        highlight: None,
+        dim: false,
+        foreground: None,
+    },
+};
+
+/// In-memory copy of the screen. A frame is drawn here first, and only the
+/// cells that differ from the previous one are sent to the terminal
+pub struct Frame {
+    cols: u16,
+    rows: u16,
+    cells: Vec<Cell>,
+    /// Where the next write lands
+    col: u16,
+    row: u16,
+    style: Style,
+    /// Every cell is drawn unstyled
+    plain: bool,
+}
+
+impl Frame {
+    pub fn new((cols, rows): (u16, u16), plain: bool) -> Self {
+        Self {
+            cols,
+            rows,
+            cells: vec![BLANK; cols as usize * rows as usize],
+            col: 0,
+            row: 0,
+            style: Style::default(),
+            plain,
+        }
+    }
+
+    pub fn goto(&mut self, col: u16, row: u16) {
+        self.col = col;
+        self.row = row;
+    }
+
+    pub fn set_foreground(&mut self, color: Option<Color>) {
+        self.style.foreground = color;
+    }
+
+    pub fn set_dim(&mut self, dim: bool) {
+        self.style.dim = dim;
+    }
+
+    pub fn write_text(&mut self, txt: &str, should_highlight: bool) {
+        self.write_highlighted(txt, should_highlight.then_some(Highlight::Reverse));
+    }
+
+    /// Writes `txt` at the current position, `\r\n` moving to the next row.
+    /// Whatever doesn't fit on the screen is cut off
+    pub fn write_highlighted(&mut self, txt: &str, highlight: Option<Highlight>) {
+        let style = if self.plain {
+            Style::default()
+        } else {
+            Style {
+                highlight,
+                ..self.style
+            }
+        };
+
+        for g in txt.graphemes(true) {
+            match g {
+                "\r" => self.col = 0,
+                "\n" => self.row = self.row.saturating_add(1),
+                "\r\n" => {
+                    self.col = 0;
+                    self.row = self.row.saturating_add(1);
+                }
+                g => {
+                    let width = grapheme_width(g) as u16;
+                    if width == 0 {
+                        continue;
+                    }
+                    if self.row < self.rows && self.col + width <= self.cols {
+                        let idx = self.row as usize * self.cols as usize + self.col as usize;
+                        let glyph = Some(Cow::Owned(g.to_string()));
+                        self.cells[idx] = Cell { glyph, style };
+                        for cell in &mut self.cells[idx + 1..idx + width as usize] {
+                            *cell = Cell { glyph: None, style };
+                        }
+                    }
+                    self.col = self.col.saturating_add(width);
+                }
+            }
+        }
+    }
+
+    /// Whether the cell at `col`, `row` is drawn with a highlight
+    #[cfg(test)]
+    pub fn is_highlighted(&self, col: u16, row: u16) -> bool {
+        let idx = row as usize * self.cols as usize + col as usize;
+        self.cells[idx].style.highlight.is_some()
+    }
+
+    /// Grapheme drawn at `col`, `row`, `None` on the right half of a wide one
+    #[cfg(test)]
+    pub fn glyph(&self, col: u16, row: u16) -> Option<&str> {
+        let idx = row as usize * self.cols as usize + col as usize;
+        self.cells[idx].glyph.as_deref()
+    }
+
+    /// Sends the frame to the terminal, skipping the cells `previous` already
+    /// drew. Without a previous frame of the same size the screen is cleared
+    /// and drawn in full
+    pub fn render(&self, previous: Option<&Frame>) -> io::Result<()> {
+        let mut out = io::stdout();
+        let previous = previous.filter(|prev| (prev.cols, prev.rows) == (self.cols, self.rows));
+        if previous.is_none() {
+            // Clearing the whole screen makes some terminals push it into
+            // the scrollback, which the main screen would fill with frames
+            queue!(out, cursor::MoveTo(0, 0), Clear(ClearType::FromCursorDown))?;
+        }
+
+        // What the terminal is set to, unknown until the first cell is drawn
+        let mut pen = None;
+        let mut at = None;
+        for (i, cell) in self.cells.iter().enumerate() {
+            let Some(glyph) = &cell.glyph else {
+                continue;
+            };
+            let unchanged = match previous {
+                Some(prev) => prev.cells[i] == *cell,
+                None => *cell == BLANK,
+            };
+            if unchanged {
+                continue;
+            }
+
+            let col = (i % self.cols as usize) as u16;
+            let row = (i / self.cols as usize) as u16;
+            if at != Some((col, row)) {
+                queue!(out, cursor::MoveTo(col, row))?;
+            }
+            if pen != Some(cell.style) {
+                apply_style(&mut out, cell.style)?;
+                pen = Some(cell.style);
+            }
+            queue!(out, Print(glyph))?;
+            at = Some((col + grapheme_width(glyph) as u16, row));
+        }
+
+        if pen.is_some_and(|pen| pen != Style::default()) {
+            queue!(out, SetAttribute(Attribute::Reset))?;
+        }
+        Ok(())
+    }
+}
+
+fn apply_style(out: &mut impl Write, cell_style: Style) -> io::Result<()> {
+    queue!(out, SetAttribute(Attribute::Reset))?;
+    if let Some(color) = cell_style.foreground {
+        queue!(out, SetForegroundColor(color))?;
+    }
+    if cell_style.dim {
+        queue!(out, SetAttribute(Attribute::Dim))?;
+    }
+    match cell_style.highlight {
+        None => {}
+        Some(Highlight::Reverse) => queue!(out, SetAttribute(Attribute::Reverse))?,
+        Some(Highlight::Bold) => queue!(out, SetAttribute(Attribute::Bold))?,
+        Some(Highlight::Underline) => queue!(out, SetAttribute(Attribute::Underlined))?,
+        Some(Highlight::Background(color)) => queue!(out, SetBackgroundColor(color))?,
+    }
+    Ok(())
+}
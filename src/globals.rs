@@ -1,6 +1,14 @@
-pub const TODO_PREFIX: &str = "TODO: ";
-pub const DONE_PREFIX: &str = "DONE: ";
 pub const TODO_INDICATOR: &str = "- [ ]";
 pub const DONE_INDICATOR: &str = "- [X]";
+pub const SOMEDAY_INDICATOR: &str = "- [~]";
 pub const BREAK: &str = "BREAK";
 pub const NO_SAVE: &str = "NO_SAVE";
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Exit status when the TODO file path is a directory
+pub const EXIT_PATH_IS_DIR: i32 = 2;
+/// Exit status when the user quit without saving
+pub const EXIT_NO_SAVE: i32 = 3;
+/// Exit status when stdout isn't a terminal the UI can be drawn on
+pub const EXIT_NOT_A_TTY: i32 = 4;
+pub const UNNAMED_REGISTER: char = '"';
+pub const CLIPBOARD_REGISTER: char = '+';
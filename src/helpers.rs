@@ -1,6 +1,8 @@
-use std::fs::File;
-use std::io::{self, BufRead, Write};
+use std::fs::OpenOptions;
+use std::io::{self, Write};
 use std::path::Path;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
 use std::time;
 
 use anyhow::Result;
@@ -9,10 +11,10 @@ use crossterm::terminal::{
     enable_raw_mode,
 };
 use crossterm::{cursor, event, queue, style};
-// TODO: Replace this dependency with builtin logic
-use unicode_width::UnicodeWidthChar;
-
-use crate::globals;
+use tick::item::{
+    SUBTASK_INDENT, entry_text, heading_text, split_done, split_due, subtask_text, today,
+};
+use unicode_segmentation::UnicodeSegmentation;
 
 pub fn clear_scr() -> io::Result<()> {
     queue!(io::stdout(), Clear(ClearType::All))?;
@@ -28,169 +30,542 @@ pub fn goto_begin() -> io::Result<()> {
     goto(0, 0)
 }
 
-pub fn init_scr() -> io::Result<()> {
-    queue!(io::stdout(), EnterAlternateScreen, cursor::Hide)?;
+/// Takes over the terminal. `inline` keeps to the main screen, scrolling what
+/// was on it up into the scrollback instead of switching to the alternate one
+pub fn init_scr(mouse: bool, inline: bool) -> io::Result<()> {
+    // Pasted text then arrives in one piece, newlines not taken for Enter
+    queue!(io::stdout(), cursor::Hide, event::EnableBracketedPaste)?;
+    if mouse {
+        queue!(io::stdout(), event::EnableMouseCapture)?;
+    }
+    if inline {
+        enable_raw_mode()?;
+        // Newlines on the last row push every row up to the prompt's out of
+        // sight, the whole screen when the terminal doesn't say where it is
+        let (_, rows) = terminal::size()?;
+        let prompt_row = cursor::position().map_or(rows, |(_, row)| row + 1);
+        queue!(
+            io::stdout(),
+            cursor::MoveTo(0, rows.saturating_sub(1)),
+            style::Print("\n".repeat(prompt_row as usize))
+        )?;
+        return goto_begin();
+    }
+
+    queue!(io::stdout(), EnterAlternateScreen)?;
     goto_begin()?;
     clear_scr()?;
     enable_raw_mode()?;
     Ok(())
 }
 
-pub fn reset_scr() -> io::Result<()> {
-    queue!(io::stdout(), LeaveAlternateScreen, cursor::Show)?;
+/// Gives the terminal back. Without the alternate screen, the last frame is
+/// left in place and the shell continues below it
+pub fn reset_scr(inline: bool) -> io::Result<()> {
+    queue!(
+        io::stdout(),
+        event::DisableMouseCapture,
+        event::DisableBracketedPaste,
+        cursor::Show
+    )?;
+    if inline {
+        let (_, rows) = terminal::size()?;
+        queue!(
+            io::stdout(),
+            cursor::MoveTo(0, rows.saturating_sub(1)),
+            style::Print("\r\n")
+        )?;
+    } else {
+        queue!(io::stdout(), LeaveAlternateScreen)?;
+    }
     disable_raw_mode()?;
     Ok(())
 }
 
-pub fn get_todos_dones<P>(path: P) -> Result<(Vec<String>, Vec<String>)>
-where
-    P: AsRef<Path>,
-{
-    let path = path.as_ref();
-    if path.is_dir() {
-        anyhow::bail!("Provided path is a directory: {:?}", path.display());
+/// Cuts `s` down to `max` characters, ending it with `…` when it's longer
+pub fn truncate_chars(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        return s.to_string();
     }
+    let mut out: String = s.chars().take(max.saturating_sub(1)).collect();
+    out.push('…');
+    out
+}
 
-    let Ok(file) = File::open(path) else {
-        return Ok(Default::default());
-    };
+/// Where a search query was found in an item, see [`fuzzy_match`]
+pub struct FuzzyMatch {
+    /// Higher is better
+    pub score: i32,
+    /// Grapheme indices of the matched text
+    pub positions: Vec<usize>,
+    /// Graphemes in the whole text
+    pub len: usize,
+}
+
+/// Finds the graphemes of `query` in `text` in order, not necessarily next
+/// to each other, ignoring case: `bm` matches `buy milk`. Each match scores a
+/// point, plus a bonus when it follows the previous one or starts a word,
+/// less the graphemes skipped before the first. An empty query matches nothing
+pub fn fuzzy_match(text: &str, query: &str) -> Option<FuzzyMatch> {
+    let query: Vec<_> = query.graphemes(true).map(str::to_lowercase).collect();
+    if query.is_empty() {
+        return None;
+    }
 
-    let reader = io::BufReader::new(file);
-    let mut todos = Vec::new();
-    let mut dones = Vec::new();
-
-    for line in reader.lines().map_while(Result::ok) {
-        if let Some(s) = line.strip_prefix(globals::TODO_PREFIX) {
-            todos.push(s.to_string());
-        } else if let Some(s) = line.strip_prefix(globals::DONE_PREFIX) {
-            dones.push(s.to_string());
-        } else {
-            anyhow::bail!("Malformed line: {:?}", line);
+    let mut wanted = query.iter().peekable();
+    let mut positions = Vec::with_capacity(query.len());
+    let mut score = 0;
+    let mut prev: Option<&str> = None;
+    let mut len = 0;
+    for (i, g) in text.graphemes(true).enumerate() {
+        len += 1;
+        if wanted.next_if(|q| g.to_lowercase() == **q).is_some() {
+            score += 1;
+            if i > 0 && positions.last() == Some(&(i - 1)) {
+                score += 3;
+            }
+            if prev.is_none_or(|p| !p.chars().any(char::is_alphanumeric)) {
+                score += 2;
+            }
+            positions.push(i);
         }
+        prev = Some(g);
     }
 
-    Ok((todos, dones))
+    if wanted.peek().is_some() {
+        return None;
+    }
+    score -= positions[0].min(10) as i32;
+    Some(FuzzyMatch {
+        score,
+        positions,
+        len,
+    })
 }
 
-pub fn split_to_fit(
-    s: &str,
-    max_width: usize,
-    offset: usize, // width to skip at the beginning (like line_begin + " ")
-) -> (&str, Vec<&str>) {
-    let mut width = 0;
+/// Byte index of the `idx`-th grapheme cluster of `s`, or its length past the end
+pub fn grapheme_to_byte(s: &str, idx: usize) -> usize {
+    s.grapheme_indices(true)
+        .nth(idx)
+        .map_or(s.len(), |(i, _)| i)
+}
 
-    for (i, c) in s.char_indices() {
-        let cw = c.width().unwrap_or(1);
+/// Removes the last grapheme cluster, so a flag or an accented letter goes as a whole
+pub fn pop_grapheme(s: &mut String) {
+    let last = s.grapheme_indices(true).next_back().map_or(0, |(i, _)| i);
+    s.truncate(last);
+}
 
-        if width + cw > max_width {
-            if i == 0 {
-                let (first_char, remainder) = s.split_at(c.len_utf8());
-                return (first_char, split_remainder(remainder, max_width, offset));
-            }
+/// Grapheme index where the word before `cursor` starts, skipping the
+/// whitespace right before it. Words are split on whitespace only
+pub fn word_start(s: &str, cursor: usize) -> usize {
+    let graphemes: Vec<&str> = s.graphemes(true).take(cursor).collect();
+    let spaces = graphemes.iter().rev().take_while(|g| is_space(g)).count();
+    let word = graphemes[..graphemes.len() - spaces]
+        .iter()
+        .rev()
+        .take_while(|g| !is_space(g))
+        .count();
+    graphemes.len() - spaces - word
+}
 
-            let (first_part, remainder) = s.split_at(i);
-            return (first_part, split_remainder(remainder, max_width, offset));
-        }
+/// Grapheme index right after the word following `cursor`, skipping the
+/// whitespace before it
+pub fn word_end(s: &str, cursor: usize) -> usize {
+    let mut rest = s.graphemes(true).skip(cursor).peekable();
+    let mut end = cursor;
+    while rest.next_if(|g| is_space(g)).is_some() {
+        end += 1;
+    }
+    while rest.next_if(|g| !is_space(g)).is_some() {
+        end += 1;
+    }
+    end
+}
 
-        width += cw;
+fn is_space(g: &str) -> bool {
+    g.chars().all(char::is_whitespace)
+}
+
+pub fn show_cursor(show: bool) -> io::Result<()> {
+    if show {
+        queue!(io::stdout(), cursor::Show)
+    } else {
+        queue!(io::stdout(), cursor::Hide)
     }
+}
 
-    (s, Vec::new())
+/// Reads the current size, later changes arrive as `Event::Resize`
+pub fn handle_term_size(term_size: &mut (u16, u16)) -> io::Result<()> {
+    *term_size = terminal::size()?;
+    Ok(())
 }
 
-fn split_remainder(s: &str, max_width: usize, offset: usize) -> Vec<&str> {
-    let mut result = Vec::new();
-    let mut current = s;
+/// Errors worth retrying, typically seen on slow or flaky terminal links
+pub fn is_transient(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
 
-    while !current.is_empty() {
-        let mut width = offset;
-        let mut split_index = current.len();
+/// Waits up to `timeout` for key, mouse or resize events, returning every
+/// one already waiting in arrival order, so a burst of autorepeated keys is
+/// handled before the next frame is drawn rather than one frame each.
+///
+/// Only key presses and repeats count. Windows terminals also report every
+/// key being released, which would otherwise act on each keystroke twice
+pub fn get_events(timeout: time::Duration) -> io::Result<Vec<event::Event>> {
+    let mut events = Vec::new();
+    let mut timeout = timeout;
+    while event::poll(timeout)? {
+        timeout = time::Duration::ZERO;
+        let event = event::read()?;
+        if is_wanted(&event) {
+            events.push(event);
+        }
+    }
+    Ok(events)
+}
 
-        for (i, c) in current.char_indices() {
-            let cw = c.width().unwrap_or(1);
+/// Whether [`get_events`] passes `event` on
+fn is_wanted(event: &event::Event) -> bool {
+    match event {
+        event::Event::Key(key) => key.kind != event::KeyEventKind::Release,
+        event::Event::Mouse(_) | event::Event::Resize(..) | event::Event::Paste(_) => true,
+        _ => false,
+    }
+}
 
-            if width + cw > max_width {
-                split_index = i;
-                break;
-            }
+/// Programs that copy stdin to the system clipboard, tried in order until one
+/// is installed
+const CLIPBOARD_COPY: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("clip.exe", &[]),
+];
+
+/// Programs that print the system clipboard, same order as `CLIPBOARD_COPY`
+const CLIPBOARD_PASTE: &[(&str, &[&str])] = &[
+    ("pbpaste", &[]),
+    ("wl-paste", &["--no-newline"]),
+    ("xclip", &["-selection", "clipboard", "-o"]),
+    ("xsel", &["--clipboard", "--output"]),
+    (
+        "powershell.exe",
+        &["-NoProfile", "-Command", "Get-Clipboard"],
+    ),
+];
+
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    for &(program, args) in CLIPBOARD_COPY {
+        let child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            child => child?,
+        };
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            anyhow::bail!("{program} exited with {status}");
+        }
+        return Ok(());
+    }
+    Err(none_found(CLIPBOARD_COPY))
+}
 
-            width += cw;
+pub fn paste_from_clipboard() -> Result<String> {
+    for &(program, args) in CLIPBOARD_PASTE {
+        let output = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+        let output = match output {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            output => output?,
+        };
+
+        if !output.status.success() {
+            anyhow::bail!("{program} exited with {}", output.status);
         }
+        return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+    }
+    Err(none_found(CLIPBOARD_PASTE))
+}
+
+/// Error naming every clipboard program tried, in the order they were
+fn none_found(programs: &[(&str, &[&str])]) -> anyhow::Error {
+    let names: Vec<_> = programs.iter().map(|&(program, _)| program).collect();
+    let (last, rest) = names.split_last().expect("clipboard programs are listed");
+    anyhow::anyhow!("none of {} or {last} found", rest.join(", "))
+}
 
-        let (chunk, remainder) = current.split_at(split_index);
-        result.push(chunk);
-        current = remainder;
+pub fn append_line<P>(path: P, line: &str) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)
+}
+
+pub fn print_lists(todos: &[String], dones: &[String], indicators: (&str, &str)) {
+    let print = |indicator: &str, entry: &String| {
+        let text = entry_text(entry);
+        match (heading_text(entry), subtask_text(entry)) {
+            (Some(_), _) => println!("{}", text),
+            (_, Some(_)) => println!("{}{} {}", SUBTASK_INDENT, indicator, text),
+            _ => println!("{} {}", indicator, text),
+        }
+    };
+    for todo in todos {
+        print(indicators.0, todo);
+    }
+    for done in dones {
+        print(indicators.1, done);
     }
+}
 
-    result
+/// Output of `tick export`
+pub enum ExportFormat {
+    Json,
+    Markdown,
 }
 
-pub fn handle_term_size(term_size: &mut (u16, u16)) -> io::Result<()> {
-    // use std::sync::LazyLock;
-    // // Program is not multithreaded/async, so it's fine for now
-    // static mut LAST_CHECK: LazyLock<time::Instant> = LazyLock::new(time::Instant::now);
-    // const DELTA: time::Duration = time::Duration::from_millis(0);
-    //
-    // #[allow(static_mut_refs)]
-    // unsafe {
-    //     let now = time::Instant::now();
-    //     if now.duration_since(*LAST_CHECK) < DELTA {
-    //         return Ok(());
-    //     }
-    //     *LAST_CHECK = now;
-    // }
-    *term_size = terminal::size()?;
-    Ok(())
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(ExportFormat::Json),
+            "md" | "markdown" => Ok(ExportFormat::Markdown),
+            _ => anyhow::bail!("Expected json or md, got {:?}", s),
+        }
+    }
 }
 
-pub fn get_key_event(timeout: time::Duration) -> io::Result<Option<event::KeyEvent>> {
-    if !event::poll(timeout)? {
-        return Ok(None);
+/// Renders the lists for other tools. JSON only holds the items' text, while
+/// Markdown keeps headings as `###` lines between them and nests subtasks
+pub fn export(todos: &[String], dones: &[String], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Json => {
+            let array = |entries: &[String]| {
+                let items: Vec<_> = entries
+                    .iter()
+                    .filter(|entry| heading_text(entry).is_none())
+                    .map(|entry| format!("    {}", json_string(entry_text(entry))))
+                    .collect();
+                if items.is_empty() {
+                    "[]".to_string()
+                } else {
+                    format!("[\n{}\n  ]", items.join(",\n"))
+                }
+            };
+            format!(
+                "{{\n  \"todos\": {},\n  \"dones\": {}\n}}\n",
+                array(todos),
+                array(dones)
+            )
+        }
+        ExportFormat::Markdown => {
+            let mut out = String::new();
+            for (checkbox, entries) in [("- [ ]", todos), ("- [x]", dones)] {
+                for entry in entries {
+                    let text = entry_text(entry);
+                    match (heading_text(entry), subtask_text(entry)) {
+                        (Some(_), _) => out += &format!("### {}\n", text),
+                        (_, Some(_)) => {
+                            out += &format!("{}{} {}\n", SUBTASK_INDENT, checkbox, text)
+                        }
+                        _ => out += &format!("{} {}\n", checkbox, text),
+                    }
+                }
+            }
+            out
+        }
     }
+}
 
-    match event::read()? {
-        event::Event::Key(event) => Ok(Some(event)),
-        _ => Ok(None),
+/// Output of `tick status`
+pub enum StatusFormat {
+    Plain,
+    Json,
+}
+
+impl FromStr for StatusFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "plain" => Ok(StatusFormat::Plain),
+            "json" => Ok(StatusFormat::Json),
+            _ => anyhow::bail!("Expected plain or json, got {:?}", s),
+        }
     }
 }
 
-pub fn save_to_file<P>(path: P, todos: &[String], dones: &[String]) -> io::Result<()>
-where
-    P: AsRef<Path>,
-{
-    let path = path.as_ref();
-    if todos.is_empty() && dones.is_empty() && !path.exists() {
-        return Ok(());
+/// Counts of the lists for a shell prompt: todos, dones, todos past their due
+/// date and items completed today. Headings aren't counted
+pub fn status(todos: &[String], dones: &[String], format: StatusFormat) -> String {
+    let today = today();
+    let is_item = |entry: &&String| heading_text(entry).is_none();
+    let todos: Vec<_> = todos.iter().filter(is_item).collect();
+    let dones: Vec<_> = dones.iter().filter(is_item).collect();
+    let overdue = todos
+        .iter()
+        .filter(|entry| split_due(entry).1.is_some_and(|due| due < today.as_str()))
+        .count();
+    let done_today = dones
+        .iter()
+        .filter(|entry| split_done(entry).1 == Some(today.as_str()))
+        .count();
+    let counts = [
+        ("todos", todos.len()),
+        ("done", dones.len()),
+        ("overdue", overdue),
+        ("done_today", done_today),
+    ];
+
+    match format {
+        StatusFormat::Plain => {
+            let counts: Vec<_> = counts
+                .iter()
+                .map(|(name, count)| format!("{name}:{count}"))
+                .collect();
+            format!("{}\n", counts.join(" "))
+        }
+        StatusFormat::Json => {
+            let counts: Vec<_> = counts
+                .iter()
+                .map(|(name, count)| format!("  {}: {count}", json_string(name)))
+                .collect();
+            format!("{{\n{}\n}}\n", counts.join(",\n"))
+        }
     }
+}
 
-    let mut file = File::create(path)?;
-    let mut write_to_file = |strs: &[String], str_begin: &str| -> io::Result<()> {
-        for s in strs {
-            writeln!(file, "{}{}", str_begin, s)?;
+fn json_string(s: &str) -> String {
+    let mut out = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out += &format!("\\u{:04x}", c as u32),
+            c => out.push(c),
         }
-        Ok(())
-    };
+    }
+    out.push('"');
+    out
+}
 
-    write_to_file(todos, globals::TODO_PREFIX)?;
-    write_to_file(dones, globals::DONE_PREFIX)?;
+/// How highlighted text stands out from the rest
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Highlight {
+    #[default]
+    Reverse,
+    Bold,
+    Underline,
+    Background(style::Color),
+}
 
-    println!("Saved state to {}", path.display());
+impl FromStr for Highlight {
+    type Err = anyhow::Error;
+
+    /// Accepts `reverse`, `bold`, `underline`, a color name or a 256-color index
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "reverse" => Self::Reverse,
+            "bold" => Self::Bold,
+            "underline" => Self::Underline,
+            _ => Self::Background(parse_color(s)?),
+        })
+    }
+}
 
-    Ok(())
+pub fn parse_color(s: &str) -> Result<style::Color> {
+    if let Ok(idx) = s.parse::<u8>() {
+        return Ok(style::Color::AnsiValue(idx));
+    }
+    style::Color::try_from(s).map_err(|_| anyhow::anyhow!("Unknown color: {:?}", s))
 }
 
-pub fn write_text(txt: &str, should_highlight: bool) -> io::Result<()> {
-    let mut handle = io::stdout();
-    if should_highlight {
-        queue!(handle, style::SetAttribute(style::Attribute::Reverse))?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_match_in_order_ignoring_case() {
+        let found = fuzzy_match("Buy Milk", "bm").unwrap();
+        assert_eq!(found.positions, [0, 4]);
+        assert_eq!(found.len, 8);
+        assert!(fuzzy_match("buy milk", "mb").is_none());
+        assert!(fuzzy_match("buy milk", "").is_none());
+
+        // Adjacent matches and word starts rank higher
+        let together = fuzzy_match("milk box", "mi").unwrap().score;
+        let apart = fuzzy_match("my list", "mi").unwrap().score;
+        assert!(together > apart);
+        let late = fuzzy_match("xxxxx milk", "mi").unwrap().score;
+        assert!(together > late);
     }
 
-    queue!(handle, style::Print(txt))?;
+    #[test]
+    fn key_releases_are_ignored() {
+        use event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+        let key = |kind| {
+            Event::Key(KeyEvent::new_with_kind(
+                KeyCode::Char('j'),
+                KeyModifiers::NONE,
+                kind,
+            ))
+        };
+        assert!(is_wanted(&key(KeyEventKind::Press)));
+        assert!(is_wanted(&key(KeyEventKind::Repeat)));
+        assert!(!is_wanted(&key(KeyEventKind::Release)));
+        assert!(is_wanted(&Event::Resize(80, 24)));
+        assert!(!is_wanted(&Event::FocusGained));
+    }
 
-    if should_highlight {
-        queue!(handle, style::SetAttribute(style::Attribute::NoReverse))?;
+    #[test]
+    fn markdown_export_leaves_out_entry_marks() {
+        use tick::item::{HEADING_MARK, SUBTASK_MARK, with_source};
+
+        let todos = [
+            with_source(&format!("{HEADING_MARK}Work"), 1),
+            with_source("report", 1),
+            with_source(&format!("{SUBTASK_MARK}outline"), 1),
+        ];
+        let dones = [with_source("call mom", 2)];
+        assert_eq!(
+            export(&todos, &dones, ExportFormat::Markdown),
+            "### Work\n- [ ] report\n  - [ ] outline\n- [x] call mom\n"
+        );
     }
-    handle.flush()?;
 
-    Ok(())
+    #[test]
+    fn clipboard_errors_name_every_program() {
+        assert_eq!(
+            none_found(CLIPBOARD_COPY).to_string(),
+            "none of pbcopy, wl-copy, xclip, xsel or clip.exe found"
+        );
+        assert!(
+            none_found(CLIPBOARD_PASTE)
+                .to_string()
+                .ends_with("or powershell.exe found")
+        );
+    }
 }
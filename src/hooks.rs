@@ -0,0 +1,37 @@
+use std::process::{Command, Stdio};
+use std::sync::mpsc::Sender;
+use std::thread;
+
+/// Runs a user hook through `sh -c` without waiting for it.
+///
+/// The item text is passed both as `$1` and as `$TICK_ITEM`. Output is
+/// discarded so it can't draw over the screen, failures are sent on `report`.
+pub fn spawn(name: &'static str, cmd: &str, item: &str, report: Sender<String>) {
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .arg("tick")
+        .arg(item)
+        .env("TICK_ITEM", item)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            _ = report.send(format!("{name} hook failed to start: {e}"));
+            return;
+        }
+    };
+
+    thread::spawn(move || {
+        let msg = match child.wait() {
+            Ok(status) if status.success() => return,
+            Ok(status) => format!("{name} hook exited with {status}"),
+            Err(e) => format!("{name} hook failed: {e}"),
+        };
+        _ = report.send(msg);
+    });
+}
@@ -0,0 +1,630 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use std::time;
+
+pub const TODO_PREFIX: &str = "TODO: ";
+pub const DONE_PREFIX: &str = "DONE: ";
+pub const SOMEDAY_PREFIX: &str = "SOMEDAY: ";
+pub const TODO_HEADING_PREFIX: &str = "TODO HEADING: ";
+pub const DONE_HEADING_PREFIX: &str = "DONE HEADING: ";
+pub const ARCHIVED_TODO_PREFIX: &str = "ARCHIVED TODO: ";
+pub const ARCHIVED_DONE_PREFIX: &str = "ARCHIVED DONE: ";
+
+/// List an item is stored under in the TODO file
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Todo,
+    Done,
+    Someday,
+    ArchivedTodo,
+    ArchivedDone,
+    TodoHeading,
+    DoneHeading,
+}
+
+impl Kind {
+    /// Every kind, in the order they are written to the file
+    pub const ALL: [Kind; 7] = [
+        Kind::Todo,
+        Kind::Done,
+        Kind::Someday,
+        Kind::ArchivedTodo,
+        Kind::ArchivedDone,
+        Kind::TodoHeading,
+        Kind::DoneHeading,
+    ];
+
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Kind::Todo => TODO_PREFIX,
+            Kind::Done => DONE_PREFIX,
+            Kind::Someday => SOMEDAY_PREFIX,
+            Kind::ArchivedTodo => ARCHIVED_TODO_PREFIX,
+            Kind::ArchivedDone => ARCHIVED_DONE_PREFIX,
+            Kind::TodoHeading => TODO_HEADING_PREFIX,
+            Kind::DoneHeading => DONE_HEADING_PREFIX,
+        }
+    }
+
+    /// Kind of the list an item of this kind is kept in, headings belonging
+    /// to the list they head
+    pub fn list(self) -> Kind {
+        match self {
+            Kind::TodoHeading => Kind::Todo,
+            Kind::DoneHeading => Kind::Done,
+            kind => kind,
+        }
+    }
+
+    /// Kind used for the headings of this list, if it can hold any
+    pub fn heading(self) -> Option<Kind> {
+        match self {
+            Kind::Todo | Kind::TodoHeading => Some(Kind::TodoHeading),
+            Kind::Done | Kind::DoneHeading => Some(Kind::DoneHeading),
+            _ => None,
+        }
+    }
+}
+
+/// Layout of the TODO file, set with `format` in the config
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// `TODO: ` / `DONE: ` prefixed lines
+    #[default]
+    Tick,
+    /// todo.txt, where done items start with `x ` and any other line is a todo
+    TodoTxt,
+}
+
+const TODO_TXT_DONE: &str = "x ";
+
+impl Format {
+    pub fn parse(self, line: &str, prefixes: &Prefixes) -> anyhow::Result<Item> {
+        if self == Format::Tick {
+            return prefixes.parse(line);
+        }
+        if let Some(text) = line.strip_prefix(TODO_TXT_DONE) {
+            // The completion date todo.txt puts first is kept like tick's own
+            let text = match text.split_once(' ') {
+                Some((date, rest)) if is_date(date) => with_done(rest, Some(date)),
+                _ => text.to_string(),
+            };
+            return Ok(Item {
+                kind: Kind::Done,
+                text,
+            });
+        }
+
+        // The lists todo.txt has no room for keep their usual prefixes
+        Ok(prefixes
+            .parse(line)
+            .ok()
+            .filter(|item| !matches!(item.kind, Kind::Todo | Kind::Done))
+            .unwrap_or_else(|| Item {
+                kind: Kind::Todo,
+                text: line.to_string(),
+            }))
+    }
+
+    /// Line of the file holding `item`
+    pub fn line<S: AsRef<str>>(self, item: &Item<S>, prefixes: &Prefixes) -> String {
+        match (self, item.kind) {
+            (Format::TodoTxt, Kind::Todo) => item.text.as_ref().to_string(),
+            (Format::TodoTxt, Kind::Done) => match split_done(item.text.as_ref()) {
+                (text, Some(date)) => format!("{}{} {}", TODO_TXT_DONE, date, text),
+                (text, None) => format!("{}{}", TODO_TXT_DONE, text),
+            },
+            _ => prefixes.line(item),
+        }
+    }
+}
+
+/// Prefixes of todo and done lines in tick files, set with `todo_prefix` and
+/// `done_prefix`. The other lists always use their usual ones
+#[derive(Clone, PartialEq, Eq)]
+pub struct Prefixes {
+    pub todo: String,
+    pub done: String,
+}
+
+impl Default for Prefixes {
+    fn default() -> Self {
+        Self {
+            todo: TODO_PREFIX.to_string(),
+            done: DONE_PREFIX.to_string(),
+        }
+    }
+}
+
+impl Prefixes {
+    pub fn of(&self, kind: Kind) -> &str {
+        match kind {
+            Kind::Todo => &self.todo,
+            Kind::Done => &self.done,
+            kind => kind.prefix(),
+        }
+    }
+
+    /// Reads a line of a tick file. The longest matching prefix wins, so a
+    /// short custom one can't take over the lines of another list
+    pub fn parse(&self, line: &str) -> anyhow::Result<Item> {
+        Kind::ALL
+            .into_iter()
+            .filter(|&kind| line.starts_with(self.of(kind)))
+            .max_by_key(|&kind| self.of(kind).len())
+            .map(|kind| Item {
+                kind,
+                text: line[self.of(kind).len()..].to_string(),
+            })
+            .ok_or_else(|| anyhow::anyhow!("Malformed line: {:?}", line))
+    }
+
+    pub fn line<S: AsRef<str>>(&self, item: &Item<S>) -> String {
+        format!("{}{}", self.of(item.kind), item.text.as_ref())
+    }
+}
+
+/// Reads the todo and done prefixes of older TODO files: `TODO:` with any
+/// spacing after it, `TODO ` without the colon, and either of them with a
+/// colon in any case (`todo:`, `Done:`). Nothing else is guessed at
+pub fn parse_legacy(line: &str) -> Option<Item> {
+    [(Kind::Todo, "TODO"), (Kind::Done, "DONE")]
+        .into_iter()
+        .find_map(|(kind, word)| {
+            let head = line.get(..word.len())?;
+            let rest = &line[word.len()..];
+            let text = match rest.strip_prefix(':') {
+                Some(text) if head.eq_ignore_ascii_case(word) => text,
+                _ if head == word => rest.strip_prefix(' ')?,
+                _ => return None,
+            };
+            Some(Item {
+                kind,
+                text: text.trim_start().to_string(),
+            })
+        })
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "tick" => Ok(Format::Tick),
+            "todo.txt" => Ok(Format::TodoTxt),
+            _ => anyhow::bail!("Expected tick or todo.txt, got {:?}", s),
+        }
+    }
+}
+
+const DUE_TOKEN: &str = "@due:";
+const DONE_TOKEN: &str = "@done:";
+const ADDED_TOKEN: &str = "@added:";
+
+/// Splits a trailing `@due:YYYY-MM-DD` off an item's text, along with the
+/// completion date after it if any. The tokens stay part of the text on disk,
+/// so items without them load and save as before
+pub fn split_due(text: &str) -> (&str, Option<&str>) {
+    split_date(split_done(text).0, DUE_TOKEN)
+}
+
+/// Splits the trailing `@done:YYYY-MM-DD` a todo gets when completed
+pub fn split_done(text: &str) -> (&str, Option<&str>) {
+    split_date(text, DONE_TOKEN)
+}
+
+/// Splits the `@added:YYYY-MM-DD` an item gets when created with
+/// `track_age`, which comes before its due and completion dates
+pub fn split_added(text: &str) -> (&str, Option<&str>) {
+    split_date(split_due(text).0, ADDED_TOKEN)
+}
+
+fn split_date<'a>(text: &'a str, token: &str) -> (&'a str, Option<&'a str>) {
+    match text.rsplit_once(token) {
+        Some((rest, date)) if is_date(date) && (rest.is_empty() || rest.ends_with(' ')) => {
+            (rest.trim_end(), Some(date))
+        }
+        _ => (text, None),
+    }
+}
+
+/// Replaces the due date of an item's text, or clears it with `None`
+pub fn with_due(text: &str, date: Option<&str>) -> String {
+    let (_, done) = split_done(text);
+    let (text, _) = split_due(text);
+    let text = match date {
+        Some(date) => format!("{} {}{}", text, DUE_TOKEN, date),
+        None => text.to_string(),
+    };
+    with_done(&text, done)
+}
+
+/// Replaces the creation date of an item's text, or clears it with `None`
+pub fn with_added(text: &str, date: Option<&str>) -> String {
+    let (_, done) = split_done(text);
+    let (_, due) = split_due(text);
+    let (text, _) = split_added(text);
+    let text = match date {
+        Some(date) => format!("{} {}{}", text, ADDED_TOKEN, date),
+        None => text.to_string(),
+    };
+    with_done(&with_due(&text, due), done)
+}
+
+/// An item's text with today as its creation date. Headings and blank items,
+/// which aren't kept, are left as they are
+pub fn stamp_added(text: &str) -> String {
+    if heading_text(text).is_some() || entry_text(text).trim().is_empty() {
+        return text.to_string();
+    }
+    with_added(text, Some(&today()))
+}
+
+/// Replaces the completion date of an item's text, or clears it with `None`
+pub fn with_done(text: &str, date: Option<&str>) -> String {
+    let (text, _) = split_done(text);
+    match date {
+        Some(date) => format!("{} {}{}", text, DONE_TOKEN, date),
+        None => text.to_string(),
+    }
+}
+
+/// Priorities `!` cycles through, highest first. Any other `(X) ` letter
+/// loads and sorts, just without a color
+pub const PRIORITIES: [char; 3] = ['A', 'B', 'C'];
+
+/// Splits a leading todo.txt style `(A) ` priority off an item's text. The
+/// marks of a subtask and its file come before it, and are left out of the
+/// text returned
+pub fn split_priority(text: &str) -> (Option<char>, &str) {
+    let text = if heading_text(text).is_some() {
+        text
+    } else {
+        entry_text(text)
+    };
+    let mut chars = text.chars();
+    match (chars.next(), chars.next(), chars.next(), chars.next()) {
+        (Some('('), Some(p @ 'A'..='Z'), Some(')'), Some(' ')) => (Some(p), &text[4..]),
+        _ => (None, text),
+    }
+}
+
+/// Replaces the priority of an item's text, or clears it with `None`
+pub fn with_priority(text: &str, priority: Option<char>) -> String {
+    let (_, rest) = split_priority(text);
+    let mut out = text[..text.len() - entry_text(text).len()].to_string();
+    if let Some(p) = priority {
+        out += &format!("({}) ", p);
+    }
+    out + rest
+}
+
+/// Removes the items whose text, trimmed, already appeared earlier in `items`,
+/// keeping the order. An item goes along with its subtasks, and subtasks are
+/// only compared with those of the same item. Headings are left alone.
+/// Returns how many entries were removed
+pub fn dedupe(items: &mut Vec<String>) -> usize {
+    let before = items.len();
+    let mut seen = HashSet::new();
+    let mut siblings = HashSet::new();
+    // Whether the item the next subtasks belong to was removed
+    let mut removing = false;
+    items.retain(|entry| {
+        let text = entry_text(entry).trim().to_string();
+        if heading_text(entry).is_some() {
+            removing = false;
+            siblings.clear();
+            return true;
+        }
+        if subtask_text(entry).is_some() {
+            return !removing && siblings.insert(text);
+        }
+        siblings.clear();
+        removing = !seen.insert(text);
+        !removing
+    });
+    before - items.len()
+}
+
+/// `text` as it can be kept on a line of its own: tabs become spaces, and
+/// other control characters, line breaks included, are dropped
+pub fn clean_text(text: &str) -> String {
+    text.chars()
+        .filter_map(|c| match c {
+            '\t' => Some(' '),
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect()
+}
+
+/// Whether `word` is a `#tag`: a `#` followed by letters, digits, `-` or `_`
+pub fn is_tag(word: &str) -> bool {
+    word.strip_prefix('#').is_some_and(|name| {
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    })
+}
+
+/// Whether an item's text carries `#tag`, ignoring case. `tag` comes without the `#`
+pub fn has_tag(text: &str, tag: &str) -> bool {
+    entry_text(text)
+        .split_whitespace()
+        .filter(|word| is_tag(word))
+        .any(|word| word[1..].to_lowercase() == tag.to_lowercase())
+}
+
+/// First `#tag` of an item's text, without the `#`
+pub fn first_tag(text: &str) -> Option<&str> {
+    entry_text(text)
+        .split_whitespace()
+        .find(|word| is_tag(word))
+        .map(|word| &word[1..])
+}
+
+/// Today's date as `YYYY-MM-DD`, in the local time zone
+pub fn today() -> String {
+    let secs = unix_secs();
+    date(local_secs(secs, utc_offset(secs)))
+}
+
+/// The local date and time as `YYYY-MM-DD HH:MM:SS +HHMM`
+pub fn timestamp() -> String {
+    let secs = unix_secs();
+    let offset = utc_offset(secs);
+    let local = local_secs(secs, offset);
+    let time = local % 86_400;
+    let (hours, minutes, seconds) = (time / 3600, time / 60 % 60, time % 60);
+    let sign = if offset < 0 { '-' } else { '+' };
+    let zone = offset.unsigned_abs() / 60;
+    format!(
+        "{} {hours:02}:{minutes:02}:{seconds:02} {sign}{:02}{:02}",
+        date(local),
+        zone / 60,
+        zone % 60
+    )
+}
+
+fn unix_secs() -> u64 {
+    time::SystemTime::now()
+        .duration_since(time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+fn local_secs(secs: u64, offset: i64) -> u64 {
+    secs.saturating_add_signed(offset)
+}
+
+/// Seconds the local time zone is ahead of UTC at Unix time `secs`
+#[cfg(unix)]
+fn utc_offset(secs: u64) -> i64 {
+    let Ok(time) = libc::time_t::try_from(secs) else {
+        return 0;
+    };
+    // SAFETY: an all-zero tm is valid, localtime_r only writes to it
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    // SAFETY: both pointers are to live locals, localtime_r is thread safe
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return 0;
+    }
+    tm.tm_gmtoff as i64
+}
+
+/// Without the C library's time zone data, dates stay in UTC
+#[cfg(not(unix))]
+fn utc_offset(_secs: u64) -> i64 {
+    0
+}
+
+/// `YYYY-MM-DD` date of a Unix time
+fn date(secs: u64) -> String {
+    // Days since the epoch to a civil date, from Howard Hinnant's algorithm
+    let z = (secs / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Days from the `YYYY-MM-DD` date `from` to `to`, `None` if either isn't one
+pub fn days_between(from: &str, to: &str) -> Option<i64> {
+    Some(days_from_epoch(to)? - days_from_epoch(from)?)
+}
+
+/// Days since 1970-01-01, the inverse of [`date`]
+fn days_from_epoch(date: &str) -> Option<i64> {
+    if !is_date(date) {
+        return None;
+    }
+    let mut parts = date.split('-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Some(era * 146_097 + doe - 719_468)
+}
+
+/// Whether `s` looks like a `YYYY-MM-DD` date
+pub fn is_date(s: &str) -> bool {
+    let parts: Vec<_> = s.split('-').collect();
+    let [year, month, day] = parts[..] else {
+        return false;
+    };
+    let number = |part: &str, len: usize, range: std::ops::RangeInclusive<u32>| {
+        part.len() == len
+            && part.bytes().all(|b| b.is_ascii_digit())
+            && part.parse().is_ok_and(|n| range.contains(&n))
+    };
+    number(year, 4, 0..=9999) && number(month, 2, 1..=12) && number(day, 2, 1..=31)
+}
+
+/// Marks a list entry as a heading while loaded. It can't be typed, so it
+/// never clashes with item text, and is swapped for the heading prefix on save
+pub const HEADING_MARK: char = '\u{1e}';
+
+/// Text of a heading entry, `None` for regular items
+pub fn heading_text(entry: &str) -> Option<&str> {
+    entry.strip_prefix(HEADING_MARK)
+}
+
+/// Marks a list entry as a subtask of the closest item above it that isn't
+/// one. In the file its line is indented by [`SUBTASK_INDENT`] instead
+pub const SUBTASK_MARK: char = '\u{1f}';
+
+/// Indent of a subtask's line in the file, before its prefix
+pub const SUBTASK_INDENT: &str = "  ";
+
+/// Text of a subtask entry, `None` for top-level items and headings
+pub fn subtask_text(entry: &str) -> Option<&str> {
+    entry.strip_prefix(SUBTASK_MARK)
+}
+
+/// Marks a list entry as loaded from another file than the first one, when
+/// several are open together. The file's number follows, `1` for the second
+/// file, and both come after the mark of a heading or subtask
+pub const SOURCE_MARK: char = '\u{1d}';
+
+/// Number of the file an entry was loaded from among those open, 0 for the
+/// first one
+pub fn entry_source(entry: &str) -> usize {
+    split_source(marked_text(entry)).0
+}
+
+/// `entry` as kept for the file numbered `source`, up to 9, its heading or
+/// subtask mark left in place
+pub fn with_source(entry: &str, source: usize) -> String {
+    let rest = marked_text(entry);
+    let mark = &entry[..entry.len() - rest.len()];
+    let (_, text) = split_source(rest);
+    match source {
+        0 => format!("{mark}{text}"),
+        n => format!("{mark}{SOURCE_MARK}{}{text}", n.min(9)),
+    }
+}
+
+/// Text of an entry without the mark of a heading or subtask, or of the file
+/// it was loaded from
+pub fn entry_text(entry: &str) -> &str {
+    split_source(marked_text(entry)).1
+}
+
+/// `entry` after the mark of a heading or subtask
+fn marked_text(entry: &str) -> &str {
+    heading_text(entry)
+        .or_else(|| subtask_text(entry))
+        .unwrap_or(entry)
+}
+
+fn split_source(text: &str) -> (usize, &str) {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next().and_then(|c| c.to_digit(10))) {
+        (Some(SOURCE_MARK), Some(n)) => (n as usize, chars.as_str()),
+        _ => (0, text),
+    }
+}
+
+/// Index of the item the subtask at `idx` belongs to, `None` when it isn't
+/// a subtask or has nothing above it
+pub fn parent_of(items: &[String], idx: usize) -> Option<usize> {
+    subtask_text(&items[idx])?;
+    (0..idx).rev().find(|&i| subtask_text(&items[i]).is_none())
+}
+
+/// Number of subtasks right below the item at `idx`, none for a subtask
+pub fn subtask_count(items: &[String], idx: usize) -> usize {
+    if subtask_text(&items[idx]).is_some() {
+        return 0;
+    }
+    let below = items[idx + 1..].iter();
+    below
+        .take_while(|entry| subtask_text(entry).is_some())
+        .count()
+}
+
+/// One line of the TODO file: the prefix of its list followed by its text.
+///
+/// Reading and writing both go through `Prefixes`, so both sides always agree
+/// on the format. `FromStr` and `Display` use the default prefixes.
+pub struct Item<S = String> {
+    pub kind: Kind,
+    pub text: S,
+}
+
+impl FromStr for Item {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> anyhow::Result<Self> {
+        Prefixes::default().parse(line)
+    }
+}
+
+impl<S: AsRef<str>> fmt::Display for Item<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&Prefixes::default().line(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dedupe_keeps_subtasks_with_their_item() {
+        let sub = |text: &str| format!("{SUBTASK_MARK}{text}");
+        let mut items = vec![
+            "A".to_string(),
+            sub("call"),
+            sub("call "),
+            "B".into(),
+            sub("call"),
+            "A ".into(),
+            sub("other"),
+            format!("{SOURCE_MARK}1B"),
+            "C".into(),
+        ];
+        assert_eq!(dedupe(&mut items), 4);
+        assert_eq!(items, ["A", &sub("call"), "B", &sub("call"), "C"]);
+    }
+
+    #[test]
+    fn item_lines_round_trip() {
+        for line in [
+            "TODO: buy milk",
+            "DONE: call mom @done:2024-05-01",
+            "SOMEDAY: learn the cello",
+            "TODO HEADING: Work",
+            "ARCHIVED DONE: old",
+            "TODO: ",
+        ] {
+            let item: Item = line.parse().unwrap();
+            assert_eq!(item.to_string(), line);
+        }
+    }
+
+    #[test]
+    fn item_parse_picks_the_longest_prefix() {
+        let item: Item = "TODO HEADING: Work".parse().unwrap();
+        assert!(item.kind == Kind::TodoHeading);
+        assert_eq!(item.text, "Work");
+
+        let item = Item {
+            kind: Kind::Done,
+            text: "x",
+        };
+        assert_eq!(item.to_string(), "DONE: x");
+        assert!("buy milk".parse::<Item>().is_err());
+    }
+}
@@ -0,0 +1,14 @@
+//! Reading, changing and saving tick's TODO files, without the terminal UI.
+//!
+//! [`TodoStore`] is the entry point: it loads a file, exposes its lists and
+//! writes them back. [`item`] holds the line formats and the helpers for the
+//! dates, priorities and tags kept in an item's text, and [`file`](mod@file) the lower
+//! level loading and saving `TodoStore` is built on. [`text`] measures and
+//! wraps items the way the UI draws them.
+
+pub mod file;
+pub mod item;
+mod store;
+pub mod text;
+
+pub use store::{Entry, TodoStore};
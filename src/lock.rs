@@ -0,0 +1,77 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+
+/// `<file>.lock` next to the TODO file, holding the PID of the tick that has
+/// it open. Removed again when dropped
+pub struct FileLock {
+    path: PathBuf,
+}
+
+impl FileLock {
+    /// Fails if another live process holds the lock. A lock left behind by a
+    /// crashed process is taken over. Returns `None` when the lock can't be
+    /// written, the file then can't be saved either and the session is
+    /// recovered elsewhere
+    pub fn acquire(file_path: &Path) -> anyhow::Result<Option<Self>> {
+        let mut name = file_path.file_name().unwrap_or_default().to_os_string();
+        name.push(".lock");
+        let path = file_path.with_file_name(name);
+
+        // Two attempts, the second after clearing a stale lock
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    writeln!(file, "{}", std::process::id())?;
+                    return Ok(Some(Self { path }));
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(_) => return Ok(None),
+            }
+
+            let owner = fs::read_to_string(&path)
+                .ok()
+                .and_then(|pid| pid.trim().parse::<u32>().ok());
+            if let Some(pid) = owner.filter(|&pid| is_alive(pid)) {
+                bail!(
+                    "{} is already open in another tick (pid {pid}). If it isn't, delete {}",
+                    file_path.display(),
+                    path.display()
+                );
+            }
+            // Another tick may have just cleared it, creating it again settles it
+            _ = fs::remove_file(&path);
+        }
+        Ok(None)
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(unix)]
+fn is_alive(pid: u32) -> bool {
+    if pid == std::process::id() {
+        return false;
+    }
+    // 0 and negative PIDs would address process groups
+    let Some(pid) = libc::pid_t::try_from(pid).ok().filter(|&pid| pid > 0) else {
+        return false;
+    };
+    // Signal 0 only checks the process exists. EPERM means it does, but
+    // belongs to another user
+    // SAFETY: kill with signal 0 sends nothing and has no other effect
+    let sent = unsafe { libc::kill(pid, 0) } == 0;
+    sent || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Without a way to check, every lock is taken as held
+#[cfg(not(unix))]
+fn is_alive(pid: u32) -> bool {
+    pid != std::process::id()
+}
@@ -1,32 +1,144 @@
-use std::path::PathBuf;
+use std::io::{self, IsTerminal};
 
-use crate::app::App;
+use tick::TodoStore;
+use tick::item::{Item, Kind};
 
-use anyhow::Context;
-use dirs::home_dir;
+use crate::app::App;
+use crate::cli::Command;
 
 mod action;
 mod app;
+mod cli;
+mod config;
+mod doctor;
+mod frame;
 mod globals;
 mod helpers;
+mod hooks;
+mod lock;
 mod screen_guard;
+mod state;
 mod tab;
 
 fn main() -> anyhow::Result<()> {
-    let untreated_path: PathBuf = match std::env::args().nth(1) {
-        Some(path) => PathBuf::from(path),
-        None => home_dir()
-            .context("Unable to find home directory")?
-            .join("TODO"),
+    let command = match cli::parse(std::env::args().skip(1)) {
+        Err(e) if e.is::<cli::PathIsDir>() => {
+            eprintln!("Error: {e}");
+            std::process::exit(globals::EXIT_PATH_IS_DIR);
+        }
+        command => command?,
     };
+    match command {
+        Command::Doctor { file_path } => {
+            if !doctor::run(&file_path) {
+                std::process::exit(1);
+            }
+        }
+        Command::Export {
+            file_path,
+            format,
+            file_format,
+            prefixes,
+        } => {
+            let store = TodoStore::load(&file_path, file_format, prefixes)?;
+            let (todos, dones) = (store.entries(Kind::Todo), store.entries(Kind::Done));
+            print!("{}", helpers::export(todos, dones, format));
+        }
+        Command::Status {
+            file_path,
+            format,
+            file_format,
+            prefixes,
+        } => {
+            let store = TodoStore::load(&file_path, file_format, prefixes)?;
+            let (todos, dones) = (store.entries(Kind::Todo), store.entries(Kind::Done));
+            print!("{}", helpers::status(todos, dones, format));
+        }
+        Command::Add {
+            file_path,
+            text,
+            done,
+            file_format,
+            prefixes,
+            backup,
+        } => {
+            let mut store = TodoStore::load(&file_path, file_format, prefixes)?;
+            store.set_backup(backup);
+            store.add(Item {
+                kind: Kind::Todo,
+                text,
+            });
+            // Dones carry the day they were completed, like in the UI
+            if done {
+                store.complete(store.entries(Kind::Todo).len() - 1);
+            }
+            store.save()?;
+            println!("Added to {}", file_path.display());
+        }
+        Command::Fmt {
+            file_path,
+            file_format,
+            prefixes,
+            backup,
+        } => {
+            let mut store = TodoStore::load(&file_path, file_format, prefixes)?;
+            store.set_backup(backup);
+            let changed = store.normalize();
+            store.save()?;
+            println!(
+                "Formatted {}, {changed} item(s) changed",
+                file_path.display()
+            );
+        }
+        Command::Version => println!("tick {}", globals::VERSION),
+        Command::Help => {
+            print!("{}", cli::USAGE);
+            println!();
+            for line in action::key_help_lines() {
+                println!("{line}");
+            }
+        }
+        Command::Keys => {
+            for line in action::key_help_lines() {
+                println!("{line}");
+            }
+        }
+        Command::Run {
+            file_path,
+            extra_paths,
+            config,
+            start_at,
+        } => {
+            // Before the file is locked, and before any escape codes land in
+            // a redirected stream
+            if !io::stdout().is_terminal() {
+                eprintln!(
+                    "Error: stdout is not a terminal, so the UI can't start. \
+                     Use `tick export` to print the lists or `tick add` to add an item"
+                );
+                std::process::exit(globals::EXIT_NOT_A_TTY);
+            }
+            screen_guard::install_panic_hook(config.no_alt_screen);
+            let mut app = App::new(file_path, *config)?;
+            for path in extra_paths {
+                app.open_extra(&path)?;
+            }
+            // Piped lines seed the list, the UI then reads keys from the terminal itself
+            if !io::stdin().is_terminal() {
+                let lines = io::stdin().lines().collect::<io::Result<Vec<_>>>()?;
+                app.add_lines(&lines);
+            }
+            if let Some(start_at) = start_at {
+                app.start_at(start_at);
+            }
+            let saved = app.run()?;
+            // `exit` skips destructors, the lock has to go first
+            drop(app);
+            if !saved {
+                std::process::exit(globals::EXIT_NO_SAVE);
+            }
+        }
+    }
 
-    let file_path = if untreated_path.is_dir() {
-        untreated_path.join("TODO")
-    } else {
-        untreated_path
-    };
-
-    let mut app = App::new(file_path)?;
-
-    app.run()
+    Ok(())
 }
@@ -1,12 +1,30 @@
+use std::io::{self, Write};
+use std::panic;
+
 use crate::helpers::reset_scr;
 
-pub struct ScreenGuard;
+pub struct ScreenGuard {
+    /// The UI was drawn on the main screen, see [`reset_scr`]
+    pub inline: bool,
+}
 
 impl Drop for ScreenGuard {
     /// Make sure to call reset_scr even if any fails happen in the main loop
     fn drop(&mut self) {
-        if let Err(e) = reset_scr() {
+        if let Err(e) = reset_scr(self.inline) {
             eprintln!("Error while resetting screen: {e}");
         }
     }
 }
+
+/// Restores the terminal before a panic message is printed. The guard only
+/// does so while unwinding, after the message went to the alternate screen
+/// and vanished with it
+pub fn install_panic_hook(inline: bool) {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        _ = reset_scr(inline);
+        _ = io::stdout().flush();
+        default_hook(info);
+    }));
+}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// UI preferences picked up where the last session left them, like the item
+/// numbers, the open tab and the cursors. Kept as `key = value` lines in a
+/// file of their own, apart from the config and the TODO file
+#[derive(Default)]
+pub struct UiState(HashMap<String, String>);
+
+impl UiState {
+    pub fn path() -> Option<PathBuf> {
+        dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .map(|dir| dir.join("tick").join("state"))
+    }
+
+    /// Reads the state file, a missing or unreadable one giving an empty state
+    pub fn load() -> Self {
+        let Some(contents) = Self::path().and_then(|path| fs::read_to_string(path).ok()) else {
+            return Self::default();
+        };
+        Self::parse(&contents)
+    }
+
+    /// Reads `key = value` lines, skipping any other
+    fn parse(contents: &str) -> Self {
+        let pairs = contents
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect();
+        Self(pairs)
+    }
+
+    /// Value of `key`, `None` when it's missing or doesn't parse
+    pub fn get<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.0.get(key)?.parse().ok()
+    }
+
+    pub fn set(&mut self, key: &str, value: impl ToString) {
+        self.0.insert(key.to_string(), value.to_string());
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        self.0.remove(key);
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.contents())
+    }
+
+    /// The `key = value` lines, sorted so the file diffs well
+    fn contents(&self) -> String {
+        let mut lines: Vec<_> = (self.0.iter())
+            .map(|(key, value)| format!("{key} = {value}\n"))
+            .collect();
+        lines.sort();
+        lines.concat()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_round_trips_and_skips_bad_lines() {
+        let state =
+            UiState::parse("tab = dones\nnot a pair\n todos_idx=3 \nshow_number = sideways\n");
+        assert_eq!(state.get::<String>("tab").as_deref(), Some("dones"));
+        assert_eq!(state.get::<usize>("todos_idx"), Some(3));
+        assert_eq!(state.get::<usize>("show_number"), None);
+        assert_eq!(state.get::<String>("missing"), None);
+
+        let mut state = state;
+        state.set("dones_idx", 7);
+        state.remove("show_number");
+        assert_eq!(
+            state.contents(),
+            "dones_idx = 7\ntab = dones\ntodos_idx = 3\n"
+        );
+        assert_eq!(
+            UiState::parse(&state.contents()).contents(),
+            state.contents()
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::file::{Backend, FileBackend, Line, TodoFile};
+use crate::item::{
+    Format, Item, Kind, Prefixes, SUBTASK_MARK, dedupe, entry_text, heading_text, subtask_text,
+    today, with_done,
+};
+
+/// A TODO file loaded in memory. The lists are changed in place and written
+/// back with [`TodoStore::save`], which keeps the comments and blank lines the
+/// file had where they were. The file lives on disk unless another
+/// [`Backend`] is given to [`TodoStore::open`]
+pub struct TodoStore<B = FileBackend> {
+    backend: B,
+    format: Format,
+    prefixes: Prefixes,
+    file: TodoFile,
+}
+
+/// An item of a list as [`TodoStore::list`] reads it, without the marks its
+/// entry is kept with
+pub struct Entry<'a> {
+    /// A heading's kind is [`Kind::TodoHeading`] or [`Kind::DoneHeading`]
+    pub item: Item<&'a str>,
+    /// Whether it belongs to the closest item above it that isn't a subtask
+    pub subtask: bool,
+}
+
+impl TodoStore {
+    /// Loads the file at `path`. A file that doesn't exist yet loads as empty
+    /// lists, and is only created once something is saved to it
+    pub fn load<P>(path: P, format: Format, prefixes: Prefixes) -> Result<Self>
+    where
+        P: Into<PathBuf>,
+    {
+        Self::open(FileBackend::new(path), format, prefixes)
+    }
+
+    /// Whether saving first copies the file as it was to `<name>.bak`, off
+    /// unless turned on here
+    pub fn set_backup(&mut self, backup: bool) {
+        self.backend.backup = backup;
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.backend.path
+    }
+}
+
+impl<B: Backend> TodoStore<B> {
+    /// Loads the lists kept in `backend`
+    pub fn open(backend: B, format: Format, prefixes: Prefixes) -> Result<Self> {
+        let file = backend.load(format, &prefixes)?;
+        Ok(Self {
+            backend,
+            format,
+            prefixes,
+            file,
+        })
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// Items of the list `kind` is kept in, headings and subtasks in place
+    pub fn list(&self, kind: Kind) -> impl Iterator<Item = Entry<'_>> {
+        let list = kind.list();
+        self.entries(kind).iter().map(move |entry| {
+            let kind = match heading_text(entry) {
+                Some(_) if list == Kind::Done => Kind::DoneHeading,
+                Some(_) => Kind::TodoHeading,
+                None => list,
+            };
+            Entry {
+                item: Item {
+                    kind,
+                    text: entry_text(entry),
+                },
+                subtask: subtask_text(entry).is_some(),
+            }
+        })
+    }
+
+    /// Entries of the list `kind` is kept in, as stored. Headings sit among
+    /// the todos and dones, starting with [`crate::item::HEADING_MARK`], and
+    /// subtasks below their item, starting with [`crate::item::SUBTASK_MARK`].
+    /// [`entry_text`] reads an entry's text without them
+    pub fn entries(&self, kind: Kind) -> &[String] {
+        let file = &self.file;
+        match kind.list() {
+            Kind::Done => &file.dones,
+            Kind::Someday => &file.someday,
+            Kind::ArchivedTodo => &file.archived_todos,
+            Kind::ArchivedDone => &file.archived_dones,
+            _ => &file.todos,
+        }
+    }
+
+    /// Entries of the list `kind` is kept in, see [`TodoStore::entries`]
+    pub fn list_mut(&mut self, kind: Kind) -> &mut Vec<String> {
+        let file = &mut self.file;
+        match kind.list() {
+            Kind::Done => &mut file.dones,
+            Kind::Someday => &mut file.someday,
+            Kind::ArchivedTodo => &mut file.archived_todos,
+            Kind::ArchivedDone => &mut file.archived_dones,
+            _ => &mut file.todos,
+        }
+    }
+
+    /// Adds `item` to the end of its list, returns the entry as stored
+    pub fn add(&mut self, item: Item) -> &str {
+        self.file.push(item)
+    }
+
+    /// Moves the todo at `idx` to the end of the dones, stamped with today's
+    /// date. `None` when there is no such todo, or it's a heading
+    pub fn complete(&mut self, idx: usize) -> Option<&str> {
+        self.move_item(Kind::Todo, idx, Kind::Done)
+    }
+
+    /// Moves the item at `idx` of the `from` list to the end of the `to` list,
+    /// returning it. Its subtasks go along under it, while a subtask moved on
+    /// its own leaves its item. Dones carry the day they were completed, any
+    /// other list drops it. Headings stay where they are
+    pub fn move_item(&mut self, from: Kind, idx: usize, to: Kind) -> Option<&str> {
+        let list = self.list_mut(from);
+        let entry = list.get(idx)?;
+        if heading_text(entry).is_some() {
+            return None;
+        }
+        let subtasks = match subtask_text(entry) {
+            Some(_) => 0,
+            None => list[idx + 1..]
+                .iter()
+                .take_while(|e| subtask_text(e).is_some())
+                .count(),
+        };
+        let block: Vec<_> = list.drain(idx..=idx + subtasks).collect();
+
+        let done = (to.list() == Kind::Done).then(today);
+        let list = self.list_mut(to);
+        let at = list.len();
+        list.extend(block.iter().enumerate().map(|(i, entry)| {
+            let text = with_done(subtask_text(entry).unwrap_or(entry), done.as_deref());
+            match i {
+                0 => text,
+                _ => format!("{SUBTASK_MARK}{text}"),
+            }
+        }));
+        list.get(at).map(String::as_str)
+    }
+
+    /// Removes later copies of items already in the list `kind` is kept in,
+    /// see [`dedupe`]. Returns how many were removed
+    pub fn dedupe(&mut self, kind: Kind) -> usize {
+        dedupe(self.list_mut(kind))
+    }
+
+    /// Puts the lists in the form saving writes anyway, so that formatting
+    /// the same file twice changes nothing: items are trimmed and blank ones
+    /// dropped, runs of blank lines become one, blank lines at either end of
+    /// the file go, and so do spaces ending a comment. Returns how many items
+    /// that changed, counting those read with a legacy prefix
+    pub fn normalize(&mut self) -> usize {
+        let mut changed = self.file.migrated;
+        for kind in [
+            Kind::Todo,
+            Kind::Done,
+            Kind::Someday,
+            Kind::ArchivedTodo,
+            Kind::ArchivedDone,
+        ] {
+            self.list_mut(kind).retain_mut(|entry| {
+                let trimmed = trim_entry(entry);
+                let keep = !entry_text(entry).is_empty();
+                if trimmed || !keep {
+                    changed += 1;
+                }
+                keep
+            });
+        }
+
+        // The lines of the items change with them, so each keeps its place
+        let layout = &mut self.file.layout;
+        layout.retain_mut(|line| match line {
+            Line::Item(_, entry) => {
+                trim_entry(entry);
+                !entry_text(entry).is_empty()
+            }
+            Line::Other(other) => {
+                other.truncate(other.trim_end().len());
+                true
+            }
+            Line::Blank => true,
+        });
+        layout.dedup_by(|a, b| matches!((a, b), (Line::Blank, Line::Blank)));
+        if matches!(layout.first(), Some(Line::Blank)) {
+            layout.remove(0);
+        }
+        if matches!(layout.last(), Some(Line::Blank)) {
+            layout.pop();
+        }
+        self.file.migrated = 0;
+        changed
+    }
+
+    /// Writes the lists back to the file
+    pub fn save(&mut self) -> io::Result<()> {
+        let file = &self.file;
+        let layout =
+            self.backend
+                .save(self.format, &self.prefixes, &file.layout, &file.sections())?;
+        self.file.layout = layout;
+        Ok(())
+    }
+}
+
+/// Trims the text of `entry`, leaving the mark of a heading or subtask in
+/// front. Returns whether anything was cut
+fn trim_entry(entry: &mut String) -> bool {
+    let text = entry_text(entry);
+    let trimmed = text.trim();
+    if trimmed.len() == text.len() {
+        return false;
+    }
+    let mark = &entry[..entry.len() - text.len()];
+    *entry = format!("{mark}{trimmed}");
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::MemoryBackend;
+
+    #[test]
+    fn complete_and_save_in_memory() {
+        let backend = MemoryBackend {
+            contents: Some("# chores\nTODO: dishes\nTODO: laundry\n".into()),
+        };
+        let mut store = TodoStore::open(backend, Format::Tick, Prefixes::default()).unwrap();
+        assert_eq!(
+            store.complete(0),
+            Some(format!("dishes @done:{}", today()).as_str())
+        );
+        store.save().unwrap();
+
+        let expected = format!("# chores\nTODO: laundry\nDONE: dishes @done:{}\n", today());
+        assert_eq!(store.backend().contents.as_deref(), Some(expected.as_str()));
+    }
+
+    #[test]
+    fn list_reads_entries_without_marks() {
+        let backend = MemoryBackend {
+            contents: Some("DONE HEADING: Shipped\nDONE: release\n  DONE: notes\n".into()),
+        };
+        let store = TodoStore::open(backend, Format::Tick, Prefixes::default()).unwrap();
+        let dones: Vec<_> = store
+            .list(Kind::Done)
+            .map(|Entry { item, subtask }| (item.kind.prefix(), item.text, subtask))
+            .collect();
+        assert_eq!(
+            dones,
+            [
+                ("DONE HEADING: ", "Shipped", false),
+                ("DONE: ", "release", false),
+                ("DONE: ", "notes", true),
+            ]
+        );
+        assert_eq!(store.list(Kind::Todo).count(), 0);
+    }
+
+    #[test]
+    fn completing_moves_the_subtasks_along() {
+        let backend = MemoryBackend {
+            contents: Some("TODO: trip\n  TODO: tickets\n  TODO: bags\nTODO: mail\n".into()),
+        };
+        let mut store = TodoStore::open(backend, Format::Tick, Prefixes::default()).unwrap();
+        store.complete(0);
+        store.move_item(Kind::Done, 2, Kind::Todo);
+        store.save().unwrap();
+
+        let done = format!("@done:{}", today());
+        let expected =
+            format!("TODO: mail\nTODO: bags\nDONE: trip {done}\n  DONE: tickets {done}\n");
+        assert_eq!(store.backend().contents.as_deref(), Some(expected.as_str()));
+    }
+}
@@ -1,15 +1,52 @@
-#[derive(Clone, Copy)]
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Tab {
     Todos,
     Dones,
+    Someday,
 }
 
 impl Tab {
+    /// Tab at the 1-based position used by the `Alt-<n>` bindings
+    pub fn nth(n: u8) -> Option<Self> {
+        match n {
+            1 => Some(Tab::Todos),
+            2 => Some(Tab::Dones),
+            3 => Some(Tab::Someday),
+            _ => None,
+        }
+    }
+
     pub fn toggle(self) -> Self {
         match self {
             Tab::Todos => Tab::Dones,
             Tab::Dones => Tab::Todos,
+            Tab::Someday => Tab::Todos,
         }
     }
 }
 
+impl fmt::Display for Tab {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Tab::Todos => "todos",
+            Tab::Dones => "dones",
+            Tab::Someday => "someday",
+        })
+    }
+}
+
+impl FromStr for Tab {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "todos" => Ok(Tab::Todos),
+            "dones" => Ok(Tab::Dones),
+            "someday" => Ok(Tab::Someday),
+            _ => Err(()),
+        }
+    }
+}
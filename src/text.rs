@@ -0,0 +1,168 @@
+//! Width of text on a terminal, and wrapping it to fit a column. Widths are
+//! counted in columns per grapheme cluster, the way the screen lays text out
+
+use unicode_segmentation::UnicodeSegmentation;
+// TODO: Replace this dependency with builtin logic
+use unicode_width::UnicodeWidthStr;
+
+/// Cuts `s` down to `max_width` columns, ending it with `…` when it doesn't fit
+pub fn truncate_to_fit(s: &str, max_width: usize) -> String {
+    if text_width(s) <= max_width {
+        return s.to_string();
+    }
+
+    let mut out = String::new();
+    let mut width = 0;
+    for g in s.graphemes(true) {
+        let gw = grapheme_width(g);
+        if width + gw + 1 > max_width {
+            break;
+        }
+        out.push_str(g);
+        width += gw;
+    }
+    out.push('…');
+    out
+}
+
+/// Splits `s` into the part on its first line and those wrapped onto the
+/// next ones, for a column `max_width` wide. The wrapped lines are drawn
+/// `offset` columns in, past the checkbox
+pub fn split_to_fit(
+    s: &str,
+    max_width: usize,
+    offset: usize, // width to skip at the beginning (like line_begin + " ")
+) -> (&str, Vec<&str>) {
+    match break_index(s, 0, offset, max_width) {
+        None => (s, Vec::new()),
+        Some(0) => {
+            let first_len = s.graphemes(true).next().map_or(0, str::len);
+            let (first_glyph, remainder) = s.split_at(first_len);
+            (first_glyph, split_remainder(remainder, max_width, offset))
+        }
+        Some(i) => {
+            let (first_part, remainder) = s.split_at(i);
+            (first_part, split_remainder(remainder, max_width, offset))
+        }
+    }
+}
+
+/// Byte index to break `s` at so the part before it fits in `max_width` when
+/// drawn from column `width`, or `None` if it all fits.
+///
+/// Breaks after the last whitespace that fits, so words stay whole. The
+/// whitespace is kept at the end of the line, keeping the pieces contiguous.
+/// Whitespace inside the first `offset` columns (the checkbox) doesn't count,
+/// and a word wider than the column is split wherever it overflows, never
+/// inside a grapheme cluster
+fn break_index(s: &str, mut width: usize, offset: usize, max_width: usize) -> Option<usize> {
+    let mut after_space = None;
+
+    for (i, g) in s.grapheme_indices(true) {
+        let gw = grapheme_width(g);
+
+        if width + gw > max_width {
+            return Some(after_space.unwrap_or(i));
+        }
+
+        width += gw;
+        if g.chars().all(char::is_whitespace) && width > offset {
+            after_space = Some(i + g.len());
+        }
+    }
+
+    None
+}
+
+fn split_remainder(s: &str, max_width: usize, offset: usize) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut current = s;
+
+    while !current.is_empty() {
+        // With no room past the indent, each line still takes one glyph
+        let split_index = match break_index(current, offset, offset, max_width) {
+            None => current.len(),
+            Some(0) => current.graphemes(true).next().map_or(0, str::len),
+            Some(i) => i,
+        };
+        let (chunk, remainder) = current.split_at(split_index);
+        result.push(chunk);
+        current = remainder;
+    }
+
+    result
+}
+
+/// Columns a grapheme cluster takes. Control chars have no width of their
+/// own but still fill a cell when drawn
+pub fn grapheme_width(g: &str) -> usize {
+    match g.chars().next() {
+        Some(c) if c.is_control() => 1,
+        _ => g.width(),
+    }
+}
+
+/// Columns `s` takes when drawn, measured the way the frame lays it out so
+/// text placed by its width lines up with what's on screen
+pub fn text_width(s: &str) -> usize {
+    s.graphemes(true).map(grapheme_width).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_at_word_boundaries() {
+        let (first, rest) = split_to_fit("- [ ] buy milk and bread", 14, 6);
+        assert_eq!(first, "- [ ] buy ");
+        // Each break keeps its space at the end of the line, which has to fit
+        assert_eq!(rest, ["milk ", "and ", "bread"]);
+
+        // The checkbox's own spaces are no place to break
+        let (first, rest) = split_to_fit("- [ ] extraordinary", 10, 6);
+        assert_eq!(first, "- [ ] extr");
+        assert_eq!(rest, ["aord", "inar", "y"]);
+
+        assert_eq!(split_to_fit("- [ ] fits", 10, 6), ("- [ ] fits", vec![]));
+    }
+
+    #[test]
+    fn wide_and_combined_graphemes_stay_whole() {
+        // e + combining acute, a wide CJK glyph, a family emoji joined by ZWJ
+        let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+        assert_eq!(text_width("e\u{301}"), 1);
+        assert_eq!(text_width("日本"), 4);
+        assert_eq!(text_width(family), 2);
+
+        let text = format!("e\u{301}日本{family}");
+        let (first, rest) = split_to_fit(&text, 4, 0);
+        assert_eq!(first, "e\u{301}日");
+        assert_eq!(rest, [format!("本{family}")]);
+    }
+
+    #[test]
+    fn narrow_widths_still_make_progress() {
+        // One glyph per line at worst, even with no room at all
+        for max_width in [0, 1] {
+            let (first, rest) = split_to_fit("- [ ] ab", max_width, 6);
+            assert_eq!(first, "-");
+            assert_eq!(rest, [" ", "[", " ", "]", " ", "a", "b"]);
+        }
+        assert_eq!(split_to_fit("", 0, 0), ("", vec![]));
+    }
+
+    #[test]
+    fn splits_a_50k_character_word() {
+        let word = "x".repeat(50_000);
+        let (first, rest) = split_to_fit(&word, 80, 6);
+
+        assert_eq!(first.len(), 80);
+        assert!(rest.iter().all(|line| line.len() <= 74));
+        assert_eq!(rest.len(), (50_000 - 80usize).div_ceil(74));
+        assert_eq!(
+            first.len() + rest.iter().map(|line| line.len()).sum::<usize>(),
+            50_000
+        );
+    }
+}